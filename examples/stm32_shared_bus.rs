@@ -1,18 +1,30 @@
-#![no_main]
-#![no_std]
+#![cfg_attr(target_arch = "arm", no_main, no_std)]
+// The example runs on an STM32F405, for other targets it builds as a stub
 
 // Setting up entry vector/panic handler and logging
+#[cfg(target_arch = "arm")]
 use cortex_m_rt::entry;
+#[cfg(target_arch = "arm")]
 use defmt_rtt as _;
+#[cfg(target_arch = "arm")]
 use panic_probe as _;
 // Imports for the shared bus
+#[cfg(target_arch = "arm")]
 use core::cell::RefCell;
+#[cfg(target_arch = "arm")]
 use embedded_hal_bus::spi::{NoDelay, RefCellDevice};
 // Hal imports
+#[cfg(target_arch = "arm")]
 use hal::prelude::*;
+#[cfg(target_arch = "arm")]
 use hal::spi::{Mode, Spi};
+#[cfg(target_arch = "arm")]
 use stm32f4xx_hal as hal;
 
+#[cfg(not(target_arch = "arm"))]
+fn main() {}
+
+#[cfg(target_arch = "arm")]
 #[entry]
 fn main() -> ! {
     // Take peripherals and set up the clocks.
@@ -48,7 +60,7 @@ fn main() -> ! {
     // SPI Bus creation using embedded-hal-bus
     let spi_bus = RefCell::new(spi3);
 
-    use ad57xx::{Ad57xxShared, Ad57xx};
+    use ad57xx::Ad57xxShared;
     // Create a new AD57x4 SpiDevice
     let mut dac = Ad57xxShared::new_ad57x4(RefCellDevice::new(&spi_bus, spi3_dac_sync, NoDelay));
 
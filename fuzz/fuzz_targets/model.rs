@@ -0,0 +1,44 @@
+//! Stream arbitrary frames into the software model and check that the driver
+//! reads back what the model holds
+#![no_main]
+
+use ad57xx::ad57x4::ChannelQuad;
+use ad57xx::model::Model;
+use ad57xx::{Ad57xx, Ad57xxShared, Command, Data, OutputRange};
+use embedded_hal::spi::SpiDevice;
+use libfuzzer_sys::fuzz_target;
+
+const CHANNELS: [ChannelQuad; 4] = [
+    ChannelQuad::DacA,
+    ChannelQuad::DacB,
+    ChannelQuad::DacC,
+    ChannelQuad::DacD,
+];
+
+fuzz_target!(|data: &[u8]| {
+    let mut model = Model::ad57x4();
+    for frame in data.chunks(3) {
+        model.write(frame).unwrap();
+    }
+    let sdo = !model.config().sdo_disable();
+    let expected: Vec<_> = (0..4)
+        .map(|addr| (model.dac_register(addr), model.range(addr)))
+        .collect();
+
+    let mut dac = Ad57xxShared::new_ad57x4(model);
+    for (chan, (code, range)) in CHANNELS.into_iter().zip(expected) {
+        let Ok(Data::DacValue(read)) = dac.read(Command::DacRegister(chan)) else {
+            panic!("DAC register readback failed");
+        };
+        let Ok(Data::OutputRange(read_range)) = dac.read(Command::RangeSelectRegister(chan)) else {
+            panic!("range readback failed");
+        };
+        if sdo {
+            assert_eq!(Some(read), code);
+            assert_eq!(Some(read_range), range);
+        } else {
+            assert_eq!(read, 0);
+            assert_eq!(read_range, OutputRange::Unipolar5V);
+        }
+    }
+});
@@ -0,0 +1,48 @@
+//! Decode arbitrary readback data for every register
+#![no_main]
+
+use ad57xx::ad57x2::ChannelDual;
+use ad57xx::ad57x4::ChannelQuad;
+use ad57xx::{Ad57xx, Ad57xxShared, Command, Function};
+use embedded_hal::spi::{ErrorType, Operation, SpiDevice};
+use libfuzzer_sys::fuzz_target;
+
+/// Device answering readbacks with the fuzzer input
+struct Replies<'a> {
+    data: core::slice::Iter<'a, u8>,
+}
+
+impl ErrorType for Replies<'_> {
+    type Error = core::convert::Infallible;
+}
+
+impl SpiDevice for Replies<'_> {
+    fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), Self::Error> {
+        for op in operations {
+            if let Operation::Transfer(rx, _) | Operation::Read(rx) = op {
+                rx.iter_mut()
+                    .for_each(|b| *b = self.data.next().copied().unwrap_or(0xFF));
+            }
+        }
+        Ok(())
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    let mut quad = Ad57xxShared::new_ad57x4(Replies { data: data.iter() });
+    for chan in [ChannelQuad::DacA, ChannelQuad::DacD, ChannelQuad::AllDacs] {
+        let _ = quad.read(Command::DacRegister(chan));
+        let _ = quad.read(Command::RangeSelectRegister(chan));
+    }
+    let _ = quad.get_power_config();
+    let _ = quad.get_config();
+    let _ = quad.read(Command::ControlRegister(Function::Load));
+
+    let mut dual = Ad57xxShared::new_ad57x2(Replies { data: data.iter() });
+    for chan in [ChannelDual::DacA, ChannelDual::DacB] {
+        let _ = dual.read(Command::DacRegister(chan));
+        let _ = dual.read(Command::RangeSelectRegister(chan));
+    }
+    let _ = dual.get_power_config();
+    let _ = dual.get_config();
+});
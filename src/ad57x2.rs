@@ -1,13 +1,14 @@
 //! Dual channel implementation
 use bitfield_struct::bitfield;
-use embedded_hal::spi::{SpiDevice, Operation};
+use embedded_hal::spi::SpiDevice;
 
-use crate::{
-    Ad57xx, Ad57xxShared, Command, Data, Error, Config, Function
-};
+use crate::register::{Control, PowerReg};
+use crate::{Ad57xx, Ad57xxShared, Config, Error};
 
 /// Dac Channel
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u8)]
 pub enum ChannelDual {
     /// DAC Channel A
@@ -55,6 +56,21 @@ pub struct PowerConfigDual {
     _unused: u8,
 }
 
+#[cfg(feature = "defmt")]
+impl defmt::Format for PowerConfigDual {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(
+            f,
+            "PowerConfigDual {{ power_up: [{}, {}], tsd: {}, overcurrent: [{}, {}] }}",
+            self.pu_a(),
+            self.pu_b(),
+            self.tsd(),
+            self.oc_a(),
+            self.oc_b()
+        )
+    }
+}
+
 impl<DEV, E> Ad57xxShared<DEV, crate::marker::Ad57x2>
 where
     DEV: SpiDevice<Error = E>,
@@ -63,18 +79,6 @@ where
     pub fn new_ad57x2(spi: DEV) -> Self {
         Self::create(spi)
     }
-    /// Power up or down a single or all DAC channels
-    /// After power up a timeout of 10us is required before loading the corresponding DAC register
-    pub fn set_power(&mut self, chan: ChannelDual, pwr: bool) -> Result<(), Error<E>> {
-        let pcfg = PowerConfigDual::from(self.pcfg);
-        self.pcfg = match chan {
-                ChannelDual::DacA => pcfg.with_pu_a(pwr),
-                ChannelDual::DacB => pcfg.with_pu_b(pwr),
-                ChannelDual::AllDacs => pcfg.with_pu_a(pwr).with_pu_b(pwr),
-        }.into();
-        self.write(Command::<ChannelDual>::PowerControlRegister, Data::PowerControl(self.pcfg.into()))
-    }    
-
 }
 impl<DEV, E> Ad57xx<DEV, E> for Ad57xxShared<DEV, crate::marker::Ad57x2> where
 DEV: SpiDevice<Error = E>,
@@ -82,26 +86,15 @@ DEV: SpiDevice<Error = E>,
     type CH = ChannelDual;
     type PCFG = PowerConfigDual;
     fn spi_write(&mut self, payload: &[u8; 3]) -> Result<(), Error<E>> {
-        self.spi
-            .transaction(&mut [Operation::Write(payload)])
-            .map_err(Error::Spi)
+        self.write_frame(payload)
     }
     fn spi_read(&mut self, cmd: u8) -> Result<u16, Error<E>> {
-        self.spi.write(&[cmd, 0, 0]).map_err(Error::Spi)?;
-        let mut rx: [u8; 3] = [0x00; 3];
-        // Send a NOP instruction (0x18) while reading
-        self.spi
-            .transfer(&mut rx, &mut [0x18, 0, 0])
-            .map_err(Error::Spi)?;
-        Ok(((rx[1] as u16) << 8) | rx[0] as u16)
+        self.read_frame(cmd)
     }    
     /// Set the device configuration
     fn set_config(&mut self, cfg: Config) -> Result<(), Error<E>> {
         self.cfg = cfg;
-        self.write(
-            Command::<Self::CH>::ControlRegister(Function::Config),
-            Data::Control(cfg),
-        )
+        self.write_register(Control::<Config>::new(), cfg)
     }
 
     /// Get the device configuration
@@ -111,15 +104,14 @@ DEV: SpiDevice<Error = E>,
     }
     #[cfg(feature = "readback")]
     fn get_config(&mut self) -> Result<Config, Error<E>> {
-        match self.read(Command::<Self::CH>::ControlRegister(Function::Config))? {
-            Data::Control(cfg) => {self.cfg = cfg; Ok(cfg)},
-            _ => Err(Error::ReadError),
-        }
+        let cfg = self.read_register(Control::<Config>::new())?;
+        self.cfg = cfg;
+        Ok(cfg)
     }    
     /// Set the device power configuration
     fn set_power_config(&mut self, pcfg: Self::PCFG) -> Result<(), Error<E>> {
         self.pcfg = pcfg.into();
-        self.write(Command::PowerControlRegister, Data::PowerControl(pcfg))
+        self.write_register(PowerReg, pcfg)
     }
 
     /// Get the device power configuration
@@ -129,11 +121,8 @@ DEV: SpiDevice<Error = E>,
     }
     #[cfg(feature = "readback")]
     fn get_power_config(&mut self) -> Result<Self::PCFG, Error<E>> {
-        if let Data::PowerControl(pcfg) = self.read(Command::PowerControlRegister)? {
-            self.pcfg = pcfg.into();
-            Ok(pcfg)
-        } else {
-            Err(Error::ReadError)
-        }
+        let pcfg = self.read_register(PowerReg)?;
+        self.pcfg = pcfg.into();
+        Ok(pcfg)
     }
 }
@@ -1,13 +1,14 @@
 //! Quad channel implementation
 use bitfield_struct::bitfield;
-use embedded_hal::spi::{Operation, SpiDevice};
+use embedded_hal::spi::SpiDevice;
 
-use crate::{
-    Ad57xx, Ad57xxShared, Command, Data, Error, Config, Function
-};
+use crate::register::{Control, PowerReg};
+use crate::{Ad57xx, Ad57xxShared, Config, Error};
 
 /// Dac Channel
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u8)]
 pub enum ChannelQuad {
     /// DAC Channel A
@@ -61,6 +62,25 @@ pub struct PowerConfigQuad {
     _unused: u8,
 }
 
+#[cfg(feature = "defmt")]
+impl defmt::Format for PowerConfigQuad {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(
+            f,
+            "PowerConfigQuad {{ power_up: [{}, {}, {}, {}], tsd: {}, overcurrent: [{}, {}, {}, {}] }}",
+            self.pu_a(),
+            self.pu_b(),
+            self.pu_c(),
+            self.pu_d(),
+            self.tsd(),
+            self.oc_a(),
+            self.oc_b(),
+            self.oc_c(),
+            self.oc_d()
+        )
+    }
+}
+
 impl<DEV, E> Ad57xxShared<DEV, crate::marker::Ad57x4>
 where
     DEV: SpiDevice<Error = E>,
@@ -69,24 +89,6 @@ where
     pub fn new_ad57x4(spi: DEV) -> Self {
         Self::create(spi)
     }
-    /// Power up or down a single or all DAC channels
-    /// After power up a timeout of 10us is required before loading the corresponding DAC register
-    pub fn set_power(&mut self, chan: ChannelQuad, pwr: bool) -> Result<(), Error<E>> {
-        let pcfg = PowerConfigQuad::from(self.pcfg);
-        self.pcfg = match chan {
-                ChannelQuad::DacA => pcfg.with_pu_a(pwr),
-                ChannelQuad::DacB => pcfg.with_pu_b(pwr),
-                ChannelQuad::DacC => pcfg.with_pu_c(pwr),
-                ChannelQuad::DacD => pcfg.with_pu_d(pwr),
-                ChannelQuad::AllDacs => pcfg
-                    .with_pu_a(pwr)
-                    .with_pu_b(pwr)
-                    .with_pu_c(pwr)
-                    .with_pu_d(pwr),
-        }.into();
-        self.write(Command::<ChannelQuad>::PowerControlRegister, Data::PowerControl(self.pcfg.into()))
-    }    
-
 }
 
 impl<DEV, E> Ad57xx<DEV, E> for Ad57xxShared<DEV, crate::marker::Ad57x4> where
@@ -94,26 +96,15 @@ DEV: SpiDevice<Error = E>{
     type CH = ChannelQuad;
     type PCFG = PowerConfigQuad;
     fn spi_write(&mut self, payload: &[u8; 3]) -> Result<(), Error<E>> {
-        self.spi
-            .transaction(&mut [Operation::Write(payload)])
-            .map_err(Error::Spi)
+        self.write_frame(payload)
     }
     fn spi_read(&mut self, cmd: u8) -> Result<u16, Error<E>> {
-        self.spi.write(&[cmd, 0, 0]).map_err(Error::Spi)?;
-        let mut rx: [u8; 3] = [0x00; 3];
-        // Send a NOP instruction (0x18) while reading
-        self.spi
-            .transfer(&mut rx, &mut [0x18, 0, 0])
-            .map_err(Error::Spi)?;
-        Ok(((rx[1] as u16) << 8) | rx[0] as u16)
+        self.read_frame(cmd)
     }    
     /// Set the device configuration
     fn set_config(&mut self, cfg: Config) -> Result<(), Error<E>> {
         self.cfg = cfg;
-        self.write(
-            Command::<Self::CH>::ControlRegister(Function::Config),
-            Data::Control(cfg),
-        )
+        self.write_register(Control::<Config>::new(), cfg)
     }
 
     /// Get the device configuration
@@ -123,16 +114,15 @@ DEV: SpiDevice<Error = E>{
     }
     #[cfg(feature = "readback")]
     fn get_config(&mut self) -> Result<Config, Error<E>> {
-        match self.read(Command::<Self::CH>::ControlRegister(Function::Config))? {
-            Data::Control(cfg) => {self.cfg = cfg; Ok(cfg)},
-            _ => Err(Error::ReadError),
-        }
+        let cfg = self.read_register(Control::<Config>::new())?;
+        self.cfg = cfg;
+        Ok(cfg)
     }
 
     /// Set the device power configuration
     fn set_power_config(&mut self, pcfg: Self::PCFG) -> Result<(), Error<E>> {
         self.pcfg = pcfg.into();
-        self.write(Command::PowerControlRegister, Data::PowerControl(pcfg))
+        self.write_register(PowerReg, pcfg)
     }
 
     /// Get the device power configuration
@@ -142,11 +132,8 @@ DEV: SpiDevice<Error = E>{
     }
     #[cfg(feature = "readback")]
     fn get_power_config(&mut self) -> Result<Self::PCFG, Error<E>> {
-        if let Data::PowerControl(pcfg) = self.read(Command::PowerControlRegister)? {
-            self.pcfg = pcfg.into();
-            Ok(pcfg)
-        } else {
-            Err(Error::ReadError)
-        }
+        let pcfg = self.read_register(PowerReg)?;
+        self.pcfg = pcfg.into();
+        Ok(pcfg)
     }
 }
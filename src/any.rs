@@ -0,0 +1,297 @@
+//! Device type chosen at runtime
+//!
+//! [`AnyAd57xx`] drives any member of the family, with the channel count and
+//! resolution taken from a [`Variant`] known at runtime. Channels are given
+//! as [`Channel`] indices and validated on every call. Codes are right
+//! aligned in the resolution of the part, voltages are rounded to it.
+//!
+//! With the `readback` feature [`probe`] tells quad and dual channel parts
+//! apart. The resolution can not be detected and has to be configured.
+//!
+//! ```ignore
+//! let variant = if rev_b { Variant::Ad5752 } else { Variant::Ad5754 };
+//! let mut dac = AnyAd57xx::new(spi, variant);
+//! dac.set_range(Channel::All, OutputRange::Bipolar10V)?;
+//! dac.set_power(Channel::All, true)?;
+//! dac.set_microvolts(Channel::Index(1), -2_500_000)?;
+//! ```
+use embedded_hal::spi::SpiDevice;
+
+use crate::ad57x2::ChannelDual;
+use crate::ad57x4::ChannelQuad;
+use crate::mapper::Chip;
+use crate::sequence::Channel;
+use crate::{private::Sealed, Ad57xx, Ad57xxShared, Config, Error, OutputRange};
+
+/// Members of the AD57xx family
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Variant {
+    /// Dual channel, 12 bit
+    Ad5722,
+    /// Dual channel, 14 bit
+    Ad5732,
+    /// Dual channel, 16 bit
+    Ad5752,
+    /// Quad channel, 12 bit
+    Ad5724,
+    /// Quad channel, 14 bit
+    Ad5734,
+    /// Quad channel, 16 bit
+    Ad5754,
+}
+
+impl Variant {
+    /// The part with the given number of channels and resolution in bits
+    pub const fn new(channels: usize, bits: u8) -> Option<Self> {
+        match (channels, bits) {
+            (2, 12) => Some(Variant::Ad5722),
+            (2, 14) => Some(Variant::Ad5732),
+            (2, 16) => Some(Variant::Ad5752),
+            (4, 12) => Some(Variant::Ad5724),
+            (4, 14) => Some(Variant::Ad5734),
+            (4, 16) => Some(Variant::Ad5754),
+            _ => None,
+        }
+    }
+
+    /// Number of channels
+    pub const fn channel_count(&self) -> usize {
+        match self {
+            Variant::Ad5722 | Variant::Ad5732 | Variant::Ad5752 => 2,
+            Variant::Ad5724 | Variant::Ad5734 | Variant::Ad5754 => 4,
+        }
+    }
+
+    /// Resolution in bits
+    pub const fn resolution(&self) -> u8 {
+        match self {
+            Variant::Ad5722 | Variant::Ad5724 => 12,
+            Variant::Ad5732 | Variant::Ad5734 => 14,
+            Variant::Ad5752 | Variant::Ad5754 => 16,
+        }
+    }
+
+    /// Highest code
+    pub const fn max_code(&self) -> u16 {
+        (1u32 << self.resolution()).wrapping_sub(1) as u16
+    }
+
+    // Position of the code in the left aligned 16 bit data
+    const fn shift(&self) -> u8 {
+        16 - self.resolution()
+    }
+}
+
+/// Detect the number of channels of the device and return the part with the
+/// given resolution.
+///
+/// The range select register of address 1, channel B of a quad channel part,
+/// is written and restored. Probe while the outputs are powered down. Returns
+/// [`Error::ReadError`] if the device does not answer, for example because
+/// SDO is disabled, and [`Error::InvalidArgument`] for an unknown resolution.
+#[cfg(feature = "readback")]
+pub fn probe<DEV, E>(spi: &mut DEV, bits: u8) -> Result<Variant, Error<E>>
+where
+    DEV: SpiDevice<Error = E>,
+{
+    use crate::{frame, read_cmd};
+
+    Variant::new(4, bits).ok_or(Error::InvalidArgument)?;
+    let mut dac = Ad57xxShared::new_ad57x4(spi);
+    // The configuration has bits set after reset and reads back as zero with
+    // SDO disabled, or all ones without a device
+    let cfg = dac.spi_read(read_cmd(0b011, 1))?;
+    if cfg == 0 || cfg > 0xF {
+        return Err(Error::ReadError);
+    }
+    let old = dac.spi_read(read_cmd(0b001, 1))?;
+    let probe = OutputRange::Bipolar10_8V as u16;
+    dac.spi_write(&frame(0b001, 1, probe))?;
+    let quad = dac.spi_read(read_cmd(0b001, 1))? == probe;
+    if quad {
+        dac.spi_write(&frame(0b001, 1, old & 0b111))?;
+    }
+    let channels = if quad { 4 } else { 2 };
+    Variant::new(channels, bits).ok_or(Error::InvalidArgument)
+}
+
+/// A device of any variant
+pub struct AnyAd57xx<DEV> {
+    chip: Chip<DEV>,
+    variant: Variant,
+}
+
+/// Run `$body` with the device and the channel converted to its channel type
+macro_rules! dispatch {
+    ($self:ident, $chan:expr, |$dac:ident, $ch:ident| $body:expr) => {
+        match &mut $self.chip {
+            Chip::Quad($dac) => {
+                let $ch = ChannelQuad::try_from($chan).map_err(|_| Error::InvalidArgument)?;
+                $body
+            }
+            Chip::Dual($dac) => {
+                let $ch = ChannelDual::try_from($chan).map_err(|_| Error::InvalidArgument)?;
+                $body
+            }
+        }
+    };
+}
+
+impl<DEV> AnyAd57xx<DEV> {
+    /// Create a device of the given variant
+    pub fn new(spi: DEV, variant: Variant) -> Self {
+        let chip = match variant.channel_count() {
+            4 => Chip::Quad(Ad57xxShared::create(spi)),
+            _ => Chip::Dual(Ad57xxShared::create(spi)),
+        };
+        AnyAd57xx { chip, variant }
+    }
+
+    /// Return the SPI device
+    pub fn destroy(self) -> DEV {
+        match self.chip {
+            Chip::Quad(dac) => dac.destroy(),
+            Chip::Dual(dac) => dac.destroy(),
+        }
+    }
+
+    /// The part
+    pub fn variant(&self) -> Variant {
+        self.variant
+    }
+
+    /// Number of channels
+    pub fn channel_count(&self) -> usize {
+        self.variant.channel_count()
+    }
+
+    /// Resolution in bits
+    pub fn resolution(&self) -> u8 {
+        self.variant.resolution()
+    }
+
+    /// Set the reference voltage in microvolts, 2.5V by default
+    pub fn set_reference_microvolts(&mut self, vref_uv: u32) {
+        match &mut self.chip {
+            Chip::Quad(dac) => dac.vref = vref_uv,
+            Chip::Dual(dac) => dac.vref = vref_uv,
+        }
+    }
+}
+
+impl<DEV, E> AnyAd57xx<DEV>
+where
+    DEV: SpiDevice<Error = E>,
+{
+    /// Write a code, right aligned in the resolution of the part, to the DAC
+    /// register of the channel(s)
+    pub fn set_code(&mut self, chan: Channel, code: u16) -> Result<(), Error<E>> {
+        if code > self.variant.max_code() {
+            return Err(Error::InvalidArgument);
+        }
+        let data = code << self.variant.shift();
+        dispatch!(self, chan, |dac, ch| dac.set_dac_output(ch, data))
+    }
+
+    /// Last written code of a single channel
+    pub fn code(&mut self, chan: Channel) -> Result<u16, Error<E>> {
+        let shift = self.variant.shift();
+        dispatch!(self, chan, |dac, ch| Ok(dac.codes[single(ch)?] >> shift))
+    }
+
+    /// Write an output voltage in microvolts to the channel(s), rounded to the
+    /// resolution of the part.
+    ///
+    /// Returns [`Error::InvalidArgument`] if the voltage is outside the range
+    /// of any of the selected channels.
+    pub fn set_microvolts(&mut self, chan: Channel, uv: i32) -> Result<(), Error<E>> {
+        let variant = self.variant;
+        dispatch!(self, chan, |dac, ch| set_microvolts(dac, ch, uv, variant))
+    }
+
+    /// Output voltage in microvolts of the last written code of a single
+    /// channel
+    pub fn microvolts(&mut self, chan: Channel) -> Result<i32, Error<E>> {
+        dispatch!(self, chan, |dac, ch| dac.dac_microvolts(ch))
+    }
+
+    /// Select the output range of the channel(s)
+    pub fn set_range(&mut self, chan: Channel, range: OutputRange) -> Result<(), Error<E>> {
+        dispatch!(self, chan, |dac, ch| dac.set_output_range(ch, range))
+    }
+
+    /// Last written output range of a single channel
+    pub fn range(&mut self, chan: Channel) -> Result<OutputRange, Error<E>> {
+        dispatch!(self, chan, |dac, ch| Ok(dac.ranges[single(ch)?]))
+    }
+
+    /// Power up or down the channel(s)
+    pub fn set_power(&mut self, chan: Channel, pwr: bool) -> Result<(), Error<E>> {
+        dispatch!(self, chan, |dac, ch| dac.set_power(ch, pwr))
+    }
+
+    /// Set the device configuration
+    pub fn set_config(&mut self, cfg: Config) -> Result<(), Error<E>> {
+        match &mut self.chip {
+            Chip::Quad(dac) => dac.set_config(cfg),
+            Chip::Dual(dac) => dac.set_config(cfg),
+        }
+    }
+
+    /// Load the DAC registers of all channels
+    pub fn load_dacs(&mut self) -> Result<(), Error<E>> {
+        match &mut self.chip {
+            Chip::Quad(dac) => dac.load_dacs(),
+            Chip::Dual(dac) => dac.load_dacs(),
+        }
+    }
+
+    /// Set the DAC registers of all channels to the clear code
+    pub fn clear_dacs(&mut self) -> Result<(), Error<E>> {
+        match &mut self.chip {
+            Chip::Quad(dac) => dac.clear_dacs(),
+            Chip::Dual(dac) => dac.clear_dacs(),
+        }
+    }
+
+    /// Read back the code of a single channel from the device
+    #[cfg(feature = "readback")]
+    pub fn read_code(&mut self, chan: Channel) -> Result<u16, Error<E>> {
+        use crate::register::DacReg;
+
+        let shift = self.variant.shift();
+        dispatch!(self, chan, |dac, ch| {
+            single(ch)?;
+            Ok(dac.read_register(DacReg(ch))? >> shift)
+        })
+    }
+}
+
+/// Address of a single channel
+fn single<CH: Into<u8>, E>(ch: CH) -> Result<usize, Error<E>> {
+    match ch.into() {
+        4 => Err(Error::InvalidArgument),
+        addr => Ok(addr as usize),
+    }
+}
+
+fn set_microvolts<DEV, IC, E>(
+    dac: &mut Ad57xxShared<DEV, IC>,
+    ch: IC::CH,
+    uv: i32,
+    variant: Variant,
+) -> Result<(), Error<E>>
+where
+    DEV: SpiDevice<Error = E>,
+    IC: Sealed,
+{
+    let frames = dac
+        .microvolts_frames(ch.into(), uv, variant.resolution())
+        .ok_or(Error::InvalidArgument)?;
+    for payload in frames.iter().flatten() {
+        dac.write_frame(payload)?;
+    }
+    Ok(())
+}
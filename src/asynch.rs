@@ -0,0 +1,184 @@
+//! Asynchronous driver
+//!
+//! [`Ad57xxAsync`] drives the device through an `embedded_hal_async`
+//! [`SpiDevice`] and keeps the same register cache as the blocking driver.
+//!
+//! Writes are cancellation safe. A frame is kept pending until its
+//! transaction has completed and the cache is only updated afterwards. If a
+//! write future is dropped before that, the next operation, or
+//! [`Ad57xxAsync::flush`], sends the interrupted frame again first. All frames
+//! written by the driver can be repeated without changing their effect.
+//!
+//! ```ignore
+//! let mut dac = Ad57xxAsync::new_ad57x4(spi);
+//! dac.set_output_range(ChannelQuad::AllDacs, OutputRange::Bipolar10V).await?;
+//! dac.set_power(ChannelQuad::AllDacs, true).await?;
+//! dac.set_dac_microvolts(ChannelQuad::DacA, -2_500_000).await?;
+//! ```
+use embedded_hal::spi::Operation;
+use embedded_hal_async::spi::SpiDevice;
+
+use crate::{frame, marker, private::Sealed, Ad57xxShared, Config, Error, Function, OutputRange};
+
+/// AD57xx DAC with an asynchronous SPI device
+pub struct Ad57xxAsync<DEV, IC> {
+    dac: Ad57xxShared<DEV, IC>,
+    // Frame that has been started but not completed
+    pending: Option<[u8; 3]>,
+}
+
+impl<DEV> Ad57xxAsync<DEV, marker::Ad57x4> {
+    /// Create a new quad channel AD57xx DAC
+    pub fn new_ad57x4(spi: DEV) -> Self {
+        Ad57xxAsync {
+            dac: Ad57xxShared::create(spi),
+            pending: None,
+        }
+    }
+}
+
+impl<DEV> Ad57xxAsync<DEV, marker::Ad57x2> {
+    /// Create a new dual channel AD57xx DAC
+    pub fn new_ad57x2(spi: DEV) -> Self {
+        Ad57xxAsync {
+            dac: Ad57xxShared::create(spi),
+            pending: None,
+        }
+    }
+}
+
+impl<DEV, IC> Ad57xxAsync<DEV, IC> {
+    /// Return the SPI device. A pending frame is discarded.
+    pub fn destroy(self) -> DEV {
+        self.dac.destroy()
+    }
+
+    /// Returns true if a write was interrupted and has not been repeated yet
+    pub fn is_pending(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    /// Set the reference voltage in microvolts, 2.5V by default
+    pub fn set_reference_microvolts(&mut self, vref_uv: u32) {
+        self.dac.vref = vref_uv;
+    }
+}
+
+impl<DEV, IC, E> Ad57xxAsync<DEV, IC>
+where
+    DEV: SpiDevice<Error = E>,
+    IC: Sealed,
+{
+    /// Complete an interrupted write
+    pub async fn flush(&mut self) -> Result<(), Error<E>> {
+        if let Some(payload) = self.pending {
+            let result = self
+                .dac
+                .spi
+                .transaction(&mut [Operation::Write(&payload)])
+                .await;
+            // Only a dropped future leaves the frame pending
+            self.pending = None;
+            result.map_err(Error::Spi)?;
+            self.dac.track(&payload);
+        }
+        Ok(())
+    }
+
+    /// Write a 24bit frame and track its effect on the driver state
+    pub(crate) async fn write_frame(&mut self, payload: &[u8; 3]) -> Result<(), Error<E>> {
+        self.flush().await?;
+        self.pending = Some(*payload);
+        self.flush().await
+    }
+
+    /// Write a code to the DAC register of the channel(s)
+    pub async fn set_dac_output(&mut self, chan: IC::CH, code: u16) -> Result<(), Error<E>> {
+        self.write_frame(&frame(0b000, chan.into(), code)).await
+    }
+
+    /// Write the code for an output voltage in microvolts to the DAC register
+    /// of the channel(s), see [`Ad57xxShared::set_dac_microvolts`]
+    pub async fn set_dac_microvolts(&mut self, chan: IC::CH, uv: i32) -> Result<(), Error<E>> {
+        for payload in self.microvolts_frames(chan, uv).await?.iter().flatten() {
+            self.write_frame(payload).await?;
+        }
+        Ok(())
+    }
+
+    /// DAC register frames for an output voltage of the channel(s), rejected
+    /// as a whole if it is outside the range of any of them
+    pub(crate) async fn microvolts_frames(
+        &mut self,
+        chan: IC::CH,
+        uv: i32,
+    ) -> Result<[Option<[u8; 3]>; 4], Error<E>> {
+        // The code depends on the cached range, complete pending writes first
+        self.flush().await?;
+        self.dac
+            .microvolts_frames(chan.into(), uv, 16)
+            .ok_or(Error::InvalidArgument)
+    }
+
+    /// Select the output range of the channel(s)
+    pub async fn set_output_range(
+        &mut self,
+        chan: IC::CH,
+        range: OutputRange,
+    ) -> Result<(), Error<E>> {
+        if range == OutputRange::InvalidReadback {
+            return Err(Error::InvalidArgument);
+        }
+        self.write_frame(&frame(0b001, chan.into(), range as u16))
+            .await
+    }
+
+    /// Power up or down the channel(s)
+    pub async fn set_power(&mut self, chan: IC::CH, pwr: bool) -> Result<(), Error<E>> {
+        // The frame is built from the cached power-up bits
+        self.flush().await?;
+        let payload = self.dac.power_frame(chan.into(), pwr);
+        self.write_frame(&payload).await
+    }
+
+    /// Set the device configuration
+    pub async fn set_config(&mut self, cfg: Config) -> Result<(), Error<E>> {
+        let payload = frame(0b011, Function::Config as u8, u8::from(cfg) as u16);
+        self.write_frame(&payload).await
+    }
+
+    /// Load the DAC registers of all channels
+    pub async fn load_dacs(&mut self) -> Result<(), Error<E>> {
+        self.write_frame(&frame(0b011, Function::Load as u8, 0))
+            .await
+    }
+
+    /// Set the DAC registers of all channels to the clear code
+    pub async fn clear_dacs(&mut self) -> Result<(), Error<E>> {
+        self.write_frame(&frame(0b011, Function::Clear as u8, 0))
+            .await
+    }
+
+    /// Last written DAC register value of a single channel
+    pub fn dac_output(&self, chan: IC::CH) -> Result<u16, Error<E>> {
+        let addr: u8 = chan.into();
+        if addr == 4 {
+            return Err(Error::InvalidArgument);
+        }
+        Ok(self.dac.codes[addr as usize])
+    }
+
+    /// Output voltage in microvolts of the last written DAC register value
+    pub fn dac_microvolts(&self, chan: IC::CH) -> Result<i32, Error<E>> {
+        let addr: u8 = chan.into();
+        if addr == 4 {
+            return Err(Error::InvalidArgument);
+        }
+        let range = self.dac.ranges[addr as usize];
+        let code = self
+            .dac
+            .coding
+            .offset_binary(range, self.dac.codes[addr as usize]);
+        Ok(range.code_to_microvolts(code, self.dac.vref))
+    }
+}
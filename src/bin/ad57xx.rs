@@ -0,0 +1,410 @@
+//! Command line tool to access an AD57xx DAC from Linux through spidev
+//!
+//! Several commands can be given at once, separated by a lone `,`. Use
+//! `--model` to run the commands against the software device model instead of
+//! a spidev device.
+use std::fmt::Debug;
+use std::process::ExitCode;
+
+use ad57xx::ad57x2::ChannelDual;
+use ad57xx::ad57x4::ChannelQuad;
+use ad57xx::decode::{DecodedConfig, DecodedFrame, DecodedPower};
+use ad57xx::model::Model;
+use ad57xx::{Ad57xx, Ad57xxShared, Command, Config, Data, Error, OutputRange};
+use linux_embedded_hal::spidev::{SpiModeFlags, SpidevOptions};
+use linux_embedded_hal::SpidevDevice;
+
+const USAGE: &str = "\
+Usage: ad57xx [OPTIONS] COMMAND [ARGS]... [, COMMAND [ARGS]...]...
+
+Options:
+  --device PATH   spidev device (default /dev/spidev0.0)
+  --speed HZ      SPI clock frequency (default 1000000)
+  --dual          the device is an AD57x2 instead of an AD57x4
+  --vref VOLTS    reference voltage for conversions (default 2.5)
+  --model         use the software device model instead of spidev
+
+Commands:
+  set CHAN CODE|VOLTS     write a code (decimal or 0x hex) or a voltage
+                          such as -2.5V, using the range read back
+  range CHAN RANGE        select uni5, uni10, uni10.8, bi5, bi10 or bi10.8
+  power CHAN on|off       power a channel up or down
+  config [FLAG]...        write the configuration, FLAG is one of
+                          sdo_disable, clr_select, clamp_enable, tsd_enable
+  clear                   set the DAC registers to the clear code
+  load                    load the DAC registers
+  dump                    read back and print all registers
+  decode FRAME            decode a 24 bit frame such as 0x018000
+
+CHAN is a, b, c, d or all (a, b or all with --dual). Voltages assume offset
+binary coding.";
+
+/// Tool level errors
+enum Failure {
+    /// The usage was requested, not an error
+    Help,
+    /// Invalid command line, the message is printed with the usage
+    Usage(String),
+    /// Device access failed
+    Device(String),
+}
+
+impl<E: Debug> From<Error<E>> for Failure {
+    fn from(e: Error<E>) -> Self {
+        Failure::Device(e.to_string())
+    }
+}
+
+fn usage<T>(msg: impl Into<String>) -> Result<T, Failure> {
+    Err(Failure::Usage(msg.into()))
+}
+
+struct Options {
+    device: String,
+    speed: u32,
+    dual: bool,
+    vref: f32,
+    model: bool,
+}
+
+/// Value written by the set command
+#[derive(Clone, Copy)]
+enum Value {
+    Code(u16),
+    Volts(f32),
+}
+
+enum Cmd<CH> {
+    Set(CH, Value),
+    Range(CH, OutputRange),
+    Power(CH, bool),
+    Config(Config),
+    Clear,
+    Load,
+    Dump,
+    Decode(u32),
+}
+
+/// Channel names of a part and the bits they select in the power register
+trait Part: Copy + Debug + 'static {
+    const CHANNELS: &'static [(&'static str, Self)];
+    const ALL: Self;
+    fn addr(self) -> u8;
+    fn frame(frame: [u8; 3]) -> DecodedFrame;
+    fn power(pcfg: u16) -> DecodedPower;
+}
+
+impl Part for ChannelQuad {
+    const CHANNELS: &'static [(&'static str, Self)] = &[
+        ("a", ChannelQuad::DacA),
+        ("b", ChannelQuad::DacB),
+        ("c", ChannelQuad::DacC),
+        ("d", ChannelQuad::DacD),
+    ];
+    const ALL: Self = ChannelQuad::AllDacs;
+    fn addr(self) -> u8 {
+        self.into()
+    }
+    fn frame(frame: [u8; 3]) -> DecodedFrame {
+        DecodedFrame::quad(frame)
+    }
+    fn power(pcfg: u16) -> DecodedPower {
+        DecodedPower::quad(pcfg)
+    }
+}
+
+impl Part for ChannelDual {
+    const CHANNELS: &'static [(&'static str, Self)] =
+        &[("a", ChannelDual::DacA), ("b", ChannelDual::DacB)];
+    const ALL: Self = ChannelDual::AllDacs;
+    fn addr(self) -> u8 {
+        self.into()
+    }
+    fn frame(frame: [u8; 3]) -> DecodedFrame {
+        DecodedFrame::dual(frame)
+    }
+    fn power(pcfg: u16) -> DecodedPower {
+        DecodedPower::dual(pcfg)
+    }
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(Failure::Help) => {
+            println!("{USAGE}");
+            ExitCode::SUCCESS
+        }
+        Err(Failure::Usage(msg)) => {
+            eprintln!("error: {msg}\n\n{USAGE}");
+            ExitCode::from(2)
+        }
+        Err(Failure::Device(msg)) => {
+            eprintln!("error: {msg}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(args: &[String]) -> Result<(), Failure> {
+    let (opts, rest) = parse_options(args)?;
+    // Decoding frames does not need a device, the model stands in for it
+    if opts.dual {
+        let cmds = parse_commands::<ChannelDual>(rest)?;
+        if opts.model || decode_only(&cmds) {
+            execute(
+                &mut Ad57xxShared::new_ad57x2(Model::ad57x2()),
+                &cmds,
+                opts.vref,
+            )
+        } else {
+            execute(
+                &mut Ad57xxShared::new_ad57x2(open(&opts)?),
+                &cmds,
+                opts.vref,
+            )
+        }
+    } else {
+        let cmds = parse_commands::<ChannelQuad>(rest)?;
+        if opts.model || decode_only(&cmds) {
+            execute(
+                &mut Ad57xxShared::new_ad57x4(Model::ad57x4()),
+                &cmds,
+                opts.vref,
+            )
+        } else {
+            execute(
+                &mut Ad57xxShared::new_ad57x4(open(&opts)?),
+                &cmds,
+                opts.vref,
+            )
+        }
+    }
+}
+
+fn parse_options(args: &[String]) -> Result<(Options, &[String]), Failure> {
+    let mut opts = Options {
+        device: "/dev/spidev0.0".into(),
+        speed: 1_000_000,
+        dual: false,
+        vref: 2.5,
+        model: false,
+    };
+    let mut i = 0;
+    while let Some(arg) = args.get(i).filter(|a| a.starts_with("--")) {
+        let mut value = || {
+            i += 1;
+            args.get(i)
+                .map(String::as_str)
+                .ok_or_else(|| Failure::Usage(format!("{arg} needs a value")))
+        };
+        match arg.as_str() {
+            "--device" => opts.device = value()?.into(),
+            "--speed" => {
+                opts.speed = value()?
+                    .parse()
+                    .or_else(|_| usage("invalid SPI clock frequency"))?
+            }
+            "--vref" => {
+                opts.vref = value()?
+                    .parse()
+                    .or_else(|_| usage("invalid reference voltage"))?
+            }
+            "--dual" => opts.dual = true,
+            "--model" => opts.model = true,
+            "--help" => return Err(Failure::Help),
+            _ => return usage(format!("unknown option {arg}")),
+        }
+        i += 1;
+    }
+    if i == args.len() {
+        return usage("no command given");
+    }
+    Ok((opts, &args[i..]))
+}
+
+fn parse_commands<CH: Part>(args: &[String]) -> Result<Vec<Cmd<CH>>, Failure> {
+    args.split(|a| a == ",").map(parse_command).collect()
+}
+
+fn parse_command<CH: Part>(args: &[String]) -> Result<Cmd<CH>, Failure> {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let cmd = match args.as_slice() {
+        ["set", chan, value] => Cmd::Set(parse_channel(chan)?, parse_value(value)?),
+        ["range", chan, range] => Cmd::Range(parse_channel(chan)?, parse_range(range)?),
+        ["power", chan, "on"] => Cmd::Power(parse_channel(chan)?, true),
+        ["power", chan, "off"] => Cmd::Power(parse_channel(chan)?, false),
+        ["config", flags @ ..] => {
+            let mut bits = 0u8;
+            for flag in flags {
+                bits |= match *flag {
+                    "sdo_disable" => 1 << 0,
+                    "clr_select" => 1 << 1,
+                    "clamp_enable" => 1 << 2,
+                    "tsd_enable" => 1 << 3,
+                    _ => return usage(format!("unknown configuration flag {flag}")),
+                };
+            }
+            Cmd::Config(Config::from(bits))
+        }
+        ["clear"] => Cmd::Clear,
+        ["load"] => Cmd::Load,
+        ["dump"] => Cmd::Dump,
+        ["decode", frame] => match parse_int(frame) {
+            Some(frame) if frame <= 0xFF_FFFF => Cmd::Decode(frame),
+            _ => return usage(format!("invalid frame {frame}")),
+        },
+        [] => return usage("empty command"),
+        [name, ..] => return usage(format!("invalid arguments for {name}")),
+    };
+    Ok(cmd)
+}
+
+fn parse_channel<CH: Part>(name: &str) -> Result<CH, Failure> {
+    if name == "all" {
+        return Ok(CH::ALL);
+    }
+    CH::CHANNELS
+        .iter()
+        .find(|(n, _)| *n == name)
+        .map(|(_, ch)| *ch)
+        .ok_or_else(|| Failure::Usage(format!("unknown channel {name}")))
+}
+
+fn parse_int(s: &str) -> Option<u32> {
+    match s.strip_prefix("0x") {
+        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
+
+fn parse_value(s: &str) -> Result<Value, Failure> {
+    if let Some(volts) = s.strip_suffix(['V', 'v']) {
+        return match volts.parse() {
+            Ok(volts) => Ok(Value::Volts(volts)),
+            Err(_) => usage(format!("invalid voltage {s}")),
+        };
+    }
+    match parse_int(s).and_then(|code| u16::try_from(code).ok()) {
+        Some(code) => Ok(Value::Code(code)),
+        None => usage(format!("invalid code {s}")),
+    }
+}
+
+fn parse_range(s: &str) -> Result<OutputRange, Failure> {
+    Ok(match s {
+        "uni5" => OutputRange::Unipolar5V,
+        "uni10" => OutputRange::Unipolar10V,
+        "uni10.8" => OutputRange::Unipolar10_8V,
+        "bi5" => OutputRange::Bipolar5V,
+        "bi10" => OutputRange::Bipolar10V,
+        "bi10.8" => OutputRange::Bipolar10_8V,
+        _ => return usage(format!("unknown range {s}")),
+    })
+}
+
+fn decode_only<CH>(cmds: &[Cmd<CH>]) -> bool {
+    cmds.iter().all(|cmd| matches!(cmd, Cmd::Decode(_)))
+}
+
+/// Open and configure the spidev device
+fn open(opts: &Options) -> Result<SpidevDevice, Failure> {
+    let mut spi = SpidevDevice::open(&opts.device)
+        .map_err(|e| Failure::Device(format!("cannot open {}: {e}", opts.device)))?;
+    spi.configure(
+        &SpidevOptions::new()
+            .bits_per_word(8)
+            .max_speed_hz(opts.speed)
+            .mode(SpiModeFlags::SPI_MODE_2)
+            .build(),
+    )
+    .map_err(|e| Failure::Device(format!("cannot configure {}: {e}", opts.device)))?;
+    Ok(spi)
+}
+
+/// Single channels selected by a channel
+fn expand<CH: Part>(chan: CH) -> impl Iterator<Item = CH> {
+    let all = chan.addr() == CH::ALL.addr();
+    CH::CHANNELS
+        .iter()
+        .map(|(_, ch)| *ch)
+        .filter(move |ch| all || ch.addr() == chan.addr())
+}
+
+fn execute<D, DEV, E, CH>(dac: &mut D, cmds: &[Cmd<CH>], vref: f32) -> Result<(), Failure>
+where
+    D: Ad57xx<DEV, E, CH = CH>,
+    E: Debug,
+    CH: Part,
+    u16: From<D::PCFG> + Into<D::PCFG>,
+    u8: From<CH> + From<Command<CH>>,
+{
+    for cmd in cmds {
+        match *cmd {
+            Cmd::Set(chan, Value::Code(code)) => dac.set_dac_output(chan, code)?,
+            Cmd::Set(chan, Value::Volts(volts)) => {
+                for ch in expand(chan) {
+                    let range = read_range(dac, ch)?;
+                    let code = range.volts_to_code(volts, vref).ok_or_else(|| {
+                        Failure::Device(format!("{volts}V is outside the range {range}"))
+                    })?;
+                    dac.set_dac_output(ch, code)?;
+                }
+            }
+            Cmd::Range(chan, range) => dac.set_output_range(chan, range)?,
+            Cmd::Power(chan, on) => {
+                let mask = expand(chan).fold(0u16, |m, ch| m | 1 << ch.addr());
+                let writable = expand(CH::ALL).fold(0u16, |m, ch| m | 1 << ch.addr());
+                let pcfg = u16::from(dac.get_power_config()?) & writable;
+                let pcfg = if on { pcfg | mask } else { pcfg & !mask };
+                dac.set_power_config(pcfg.into())?;
+            }
+            Cmd::Config(cfg) => dac.set_config(cfg)?,
+            Cmd::Clear => dac.clear_dacs()?,
+            Cmd::Load => dac.load_dacs()?,
+            Cmd::Dump => dump(dac)?,
+            Cmd::Decode(frame) => {
+                let [_, cmd, hi, lo] = frame.to_be_bytes();
+                println!("{}", CH::frame([cmd, hi, lo]));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn read_range<D, DEV, E, CH>(dac: &mut D, chan: CH) -> Result<OutputRange, Error<E>>
+where
+    D: Ad57xx<DEV, E, CH = CH>,
+    CH: Part,
+    u16: From<D::PCFG> + Into<D::PCFG>,
+    u8: From<CH> + From<Command<CH>>,
+{
+    match dac.read(Command::RangeSelectRegister(chan))? {
+        Data::OutputRange(range) => Ok(range),
+        _ => Err(Error::ReadError),
+    }
+}
+
+fn dump<D, DEV, E, CH>(dac: &mut D) -> Result<(), Failure>
+where
+    D: Ad57xx<DEV, E, CH = CH>,
+    E: Debug,
+    CH: Part,
+    u16: From<D::PCFG> + Into<D::PCFG>,
+    u8: From<CH> + From<Command<CH>>,
+{
+    for &(name, chan) in CH::CHANNELS {
+        let code = match dac.read(Command::DacRegister(chan))? {
+            Data::DacValue(code) => code,
+            _ => return Err(Error::<E>::ReadError.into()),
+        };
+        let range = read_range(dac, chan)?;
+        println!("dac {name}   0x{code:04X}  {range}");
+    }
+    let pcfg = u16::from(dac.get_power_config()?);
+    println!("power   0x{pcfg:04X}  {}", CH::power(pcfg));
+    let cfg = u8::from(dac.get_config()?);
+    println!("config  0x{cfg:02X}    {}", DecodedConfig(cfg));
+    Ok(())
+}
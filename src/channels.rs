@@ -0,0 +1,259 @@
+//! Arbitrary subsets of the channels of a part
+//!
+//! A [`ChannelSet`] is a bitmask of channel addresses. Operations on a set
+//! use the broadcast address when the set covers every channel and a frame
+//! per channel otherwise. Power control always takes a single frame.
+//!
+//! The methods of [`Ad57xxShared`] taking a set shadow the [`Ad57xx`](crate::Ad57xx)
+//! trait methods of the same name, which remain available to code generic
+//! over the trait.
+//!
+//! ```ignore
+//! let set = ChannelQuad::DacA | ChannelQuad::DacC;
+//! dac.set_power(set, true)?;
+//! dac.set_output_range(set, OutputRange::Bipolar5V)?;
+//! dac.set_dac_output(set, 0x8000)?;
+//! ```
+use core::marker::PhantomData;
+use core::ops::{BitOr, BitOrAssign};
+
+use embedded_hal::spi::SpiDevice;
+
+use crate::ad57x2::ChannelDual;
+use crate::ad57x4::ChannelQuad;
+use crate::{frame, marker, private::Sealed, Ad57xxShared, Error, OutputRange};
+
+/// Address of the channel(s), 4 selects all channels
+const ALL: u8 = 4;
+
+/// A set of channels of the part `IC`
+pub struct ChannelSet<IC> {
+    // Bit n is set for the channel at address n
+    mask: u8,
+    _ic: PhantomData<IC>,
+}
+
+impl<IC> Clone for ChannelSet<IC> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<IC> Copy for ChannelSet<IC> {}
+
+impl<IC> PartialEq for ChannelSet<IC> {
+    fn eq(&self, other: &Self) -> bool {
+        self.mask == other.mask
+    }
+}
+
+impl<IC> Eq for ChannelSet<IC> {}
+
+impl<IC> core::fmt::Debug for ChannelSet<IC> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "ChannelSet({:#06b})", self.mask)
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl<IC> defmt::Format for ChannelSet<IC> {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "ChannelSet({=u8:#06b})", self.mask)
+    }
+}
+
+impl<IC: Sealed> Default for ChannelSet<IC> {
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+impl<IC: Sealed> ChannelSet<IC> {
+    /// The empty set
+    pub const fn empty() -> Self {
+        ChannelSet {
+            mask: 0,
+            _ic: PhantomData,
+        }
+    }
+
+    /// All channels of the part
+    pub const fn all() -> Self {
+        ChannelSet {
+            mask: IC::PU_MASK as u8,
+            _ic: PhantomData,
+        }
+    }
+
+    /// The set of the given channels
+    pub fn from_channels(chans: &[IC::CH]) -> Self {
+        chans
+            .iter()
+            .fold(Self::empty(), |set, &chan| set.with(chan))
+    }
+
+    /// Bitmask of the channel addresses, in the layout of the power-up bits
+    /// of the power control register
+    pub fn bits(&self) -> u8 {
+        self.mask
+    }
+
+    /// Add the channel(s)
+    pub fn with(self, chan: IC::CH) -> Self {
+        ChannelSet {
+            mask: self.mask | Self::mask_of(chan),
+            _ic: PhantomData,
+        }
+    }
+
+    /// Remove the channel(s)
+    pub fn without(self, chan: IC::CH) -> Self {
+        ChannelSet {
+            mask: self.mask & !Self::mask_of(chan),
+            _ic: PhantomData,
+        }
+    }
+
+    /// Returns true if the set contains the channel(s)
+    pub fn contains(&self, chan: IC::CH) -> bool {
+        let mask = Self::mask_of(chan);
+        self.mask & mask == mask
+    }
+
+    /// Returns true if no channel is selected
+    pub fn is_empty(&self) -> bool {
+        self.mask == 0
+    }
+
+    /// Returns true if every channel of the part is selected
+    pub fn is_all(&self) -> bool {
+        self.mask == IC::PU_MASK as u8
+    }
+
+    /// Number of selected channels
+    pub fn len(&self) -> usize {
+        self.mask.count_ones() as usize
+    }
+
+    /// The selected channels, in address order
+    pub fn iter(&self) -> impl Iterator<Item = IC::CH> {
+        let mask = self.mask;
+        IC::CHANNELS
+            .iter()
+            .copied()
+            .filter(move |&chan| mask & Self::mask_of(chan) != 0)
+    }
+
+    /// Addresses to write to for the set, the broadcast address if the set
+    /// covers every channel
+    fn addresses(&self) -> impl Iterator<Item = u8> {
+        let mask = self.mask;
+        let all = self.is_all();
+        core::iter::once(ALL).filter(move |_| all).chain(
+            IC::ADDRESSES
+                .iter()
+                .copied()
+                .filter(move |&addr| !all && mask & 1 << addr != 0),
+        )
+    }
+
+    fn mask_of(chan: IC::CH) -> u8 {
+        match chan.into() {
+            ALL => IC::PU_MASK as u8,
+            addr => 1 << addr,
+        }
+    }
+}
+
+impl<IC: Sealed> BitOr for ChannelSet<IC> {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        ChannelSet {
+            mask: self.mask | rhs.mask,
+            _ic: PhantomData,
+        }
+    }
+}
+
+impl<IC: Sealed> BitOrAssign for ChannelSet<IC> {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.mask |= rhs.mask;
+    }
+}
+
+macro_rules! channel_set {
+    ($ch:ty, $ic:ty) => {
+        impl From<$ch> for ChannelSet<$ic> {
+            fn from(chan: $ch) -> Self {
+                ChannelSet::empty().with(chan)
+            }
+        }
+
+        impl BitOr for $ch {
+            type Output = ChannelSet<$ic>;
+
+            fn bitor(self, rhs: Self) -> ChannelSet<$ic> {
+                ChannelSet::from(self).with(rhs)
+            }
+        }
+
+        impl BitOr<$ch> for ChannelSet<$ic> {
+            type Output = Self;
+
+            fn bitor(self, rhs: $ch) -> Self {
+                self.with(rhs)
+            }
+        }
+    };
+}
+
+channel_set!(ChannelQuad, marker::Ad57x4);
+channel_set!(ChannelDual, marker::Ad57x2);
+
+impl<DEV, IC, E> Ad57xxShared<DEV, IC>
+where
+    DEV: SpiDevice<Error = E>,
+    IC: Sealed,
+{
+    /// Power up or down the channel(s) in a single frame.
+    /// After power up a timeout of 10us is required before loading the corresponding DAC register
+    pub fn set_power(
+        &mut self,
+        chans: impl Into<ChannelSet<IC>>,
+        pwr: bool,
+    ) -> Result<(), Error<E>> {
+        let mask = chans.into().bits() as u16;
+        // Only the power-up bits are writable, status bits read back stay out
+        let pcfg = self.pcfg & IC::PU_MASK;
+        let pcfg = if pwr { pcfg | mask } else { pcfg & !mask };
+        self.write_frame(&frame(0b010, 0, pcfg))
+    }
+
+    /// Write a code to the DAC registers of the channel(s)
+    pub fn set_dac_output(
+        &mut self,
+        chans: impl Into<ChannelSet<IC>>,
+        code: u16,
+    ) -> Result<(), Error<E>> {
+        for addr in chans.into().addresses() {
+            self.write_frame(&frame(0b000, addr, code))?;
+        }
+        Ok(())
+    }
+
+    /// Select the output range of the channel(s)
+    pub fn set_output_range(
+        &mut self,
+        chans: impl Into<ChannelSet<IC>>,
+        range: OutputRange,
+    ) -> Result<(), Error<E>> {
+        if range == OutputRange::InvalidReadback {
+            return Err(Error::InvalidArgument);
+        }
+        for addr in chans.into().addresses() {
+            self.write_frame(&frame(0b001, addr, range as u16))?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,105 @@
+//! Coding of the DAC registers for the bipolar output ranges
+//!
+//! The BIN/~2sCOMPLEMENT pin selects whether bipolar codes are interpreted as
+//! offset binary or two's complement. The unipolar ranges always use straight
+//! binary coding.
+//!
+//! ```ignore
+//! // BIN/~2sCOMPLEMENT tied low
+//! dac.set_coding(Coding::TwosComplement);
+//! dac.set_output_range(ChannelQuad::DacA, OutputRange::Bipolar5V)?;
+//! dac.set_dac_signed(ChannelQuad::DacA, -0x4000)?; // -2.5V
+//! ```
+use embedded_hal::spi::SpiDevice;
+
+#[cfg(feature = "readback")]
+use crate::read_cmd;
+use crate::{frame, private::Sealed, Ad57xxShared, Error, OutputRange};
+
+/// State of the BIN/~2sCOMPLEMENT pin
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Coding {
+    /// BIN/~2sCOMPLEMENT tied high, bipolar codes are offset binary
+    #[default]
+    OffsetBinary,
+    /// BIN/~2sCOMPLEMENT tied low, bipolar codes are two's complement
+    TwosComplement,
+}
+
+impl Coding {
+    /// Encode a signed, left-aligned value into a DAC register value.
+    ///
+    /// -32768 corresponds to negative full scale and 32767 to positive full
+    /// scale. Parts with a bit depth smaller than 16 ignore the lower bits, so
+    /// a 12 bit value `v` is passed as `v << 4`.
+    pub fn encode(&self, val: i16) -> u16 {
+        match self {
+            Coding::OffsetBinary => (val as u16) ^ 0x8000,
+            Coding::TwosComplement => val as u16,
+        }
+    }
+
+    /// Decode a DAC register value of a bipolar range into a signed,
+    /// left-aligned value.
+    pub fn decode(&self, code: u16) -> i16 {
+        match self {
+            Coding::OffsetBinary => (code ^ 0x8000) as i16,
+            Coding::TwosComplement => code as i16,
+        }
+    }
+
+    /// Convert between a DAC register value in this coding and offset binary
+    pub(crate) fn offset_binary(&self, range: OutputRange, code: u16) -> u16 {
+        match self {
+            Coding::TwosComplement if range.is_bipolar() => code ^ 0x8000,
+            _ => code,
+        }
+    }
+}
+
+impl<DEV, IC, E> Ad57xxShared<DEV, IC>
+where
+    DEV: SpiDevice<Error = E>,
+    IC: Sealed,
+{
+    /// Set the coding selected by the BIN/~2sCOMPLEMENT pin
+    pub fn set_coding(&mut self, coding: Coding) {
+        self.coding = coding;
+    }
+
+    /// Get the coding selected by the BIN/~2sCOMPLEMENT pin
+    pub fn coding(&self) -> Coding {
+        self.coding
+    }
+
+    /// Write a signed, left-aligned value to the DAC register of a channel
+    /// with a bipolar output range, see [`Coding::encode`].
+    ///
+    /// Returns [`Error::InvalidArgument`] if any of the selected channels is
+    /// set to a unipolar range.
+    pub fn set_dac_signed(&mut self, chan: IC::CH, val: i16) -> Result<(), Error<E>> {
+        let addr: u8 = chan.into();
+        let unipolar = IC::ADDRESSES
+            .iter()
+            .filter(|&&a| addr == 4 || a == addr)
+            .any(|&a| !self.ranges[a as usize].is_bipolar());
+        if unipolar {
+            return Err(Error::InvalidArgument);
+        }
+        self.write_frame(&frame(0b000, addr, self.coding.encode(val)))
+    }
+
+    /// Read back the DAC register of a channel with a bipolar output range
+    /// as a signed, left-aligned value.
+    #[cfg(feature = "readback")]
+    pub fn get_dac_signed(&mut self, chan: IC::CH) -> Result<i16, Error<E>> {
+        let addr: u8 = chan.into();
+        if addr == 4 || !self.ranges[addr as usize].is_bipolar() {
+            return Err(Error::InvalidArgument);
+        }
+        let code = self.read_frame(read_cmd(0b000, addr))?;
+        Ok(self.coding.decode(code))
+    }
+}
@@ -0,0 +1,268 @@
+//! Control voltages for synthesizer modules
+//!
+//! Volts-per-octave pitch outputs with calibration, scale quantisation,
+//! transposition and glide, plus gate and trigger outputs. Notes are counted
+//! in semitones from 0V, so note 12 is 1V and note -24 is -2V. The channels
+//! are tick driven: call `tick` at a fixed rate to advance glides and
+//! triggers.
+//!
+//! ```ignore
+//! dac.set_output_range(ChannelQuad::AllDacs, OutputRange::Bipolar10V)?;
+//! let mut pitch = PitchChannel::new(ChannelQuad::DacA)
+//!     .with_scale(Scale::Minor)
+//!     .with_glide(1_000);
+//! let mut gate = GateChannel::new(ChannelQuad::DacB);
+//! pitch.note_on(14);
+//! gate.set(&mut dac, true)?;
+//! loop {
+//!     pitch.tick(&mut dac)?;
+//!     gate.tick(&mut dac)?;
+//! }
+//! ```
+use embedded_hal::spi::SpiDevice;
+
+use crate::{private::Sealed, Ad57xxShared, Error};
+
+const UV_PER_OCTAVE: i64 = 1_000_000;
+
+/// Set of allowed semitones within an octave
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Scale {
+    /// All twelve semitones
+    Chromatic,
+    /// Major (ionian) scale
+    Major,
+    /// Natural minor (aeolian) scale
+    Minor,
+    /// User defined scale, bit `n` allows the semitone `n` above the root.
+    /// Bits above bit 11 are ignored.
+    Custom(
+        #[cfg_attr(
+            feature = "serde",
+            serde(deserialize_with = "crate::serialize::scale_mask")
+        )]
+        u16,
+    ),
+}
+
+impl Scale {
+    /// Bitmask of the allowed semitones
+    pub fn mask(&self) -> u16 {
+        match self {
+            Scale::Chromatic => 0x0FFF,
+            Scale::Major => 0b1010_1011_0101,
+            Scale::Minor => 0b0101_1010_1101,
+            Scale::Custom(mask) => mask & 0x0FFF,
+        }
+    }
+
+    /// Snap a note to the nearest note in the scale, ties resolve downwards.
+    /// An empty scale returns the note unchanged.
+    pub fn quantize(&self, note: i16) -> i16 {
+        let mask = self.mask();
+        if mask == 0 {
+            return note;
+        }
+        for distance in 0..=6 {
+            for candidate in [note.saturating_sub(distance), note.saturating_add(distance)] {
+                if mask & (1 << candidate.rem_euclid(12)) != 0 {
+                    return candidate;
+                }
+            }
+        }
+        note
+    }
+}
+
+/// Per channel calibration of the pitch output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Calibration {
+    /// Scale error in parts per million, positive values stretch the octave
+    pub scale_ppm: i32,
+    /// Offset added to the output in microvolts
+    pub offset_uv: i32,
+}
+
+impl Calibration {
+    /// Output voltage in microvolts for a note
+    pub fn note_to_microvolts(&self, note: i16) -> i32 {
+        let uv = note as i64 * UV_PER_OCTAVE;
+        let uv = uv + uv * self.scale_ppm as i64 / 1_000_000;
+        let uv = (uv + 6 * uv.signum()) / 12;
+        (uv + self.offset_uv as i64) as i32
+    }
+}
+
+/// A volts-per-octave pitch output
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PitchChannel<CH> {
+    chan: CH,
+    calibration: Calibration,
+    scale: Scale,
+    transpose: i16,
+    glide: u32,
+    current: Option<i32>,
+    target: i32,
+}
+
+impl<CH: Copy> PitchChannel<CH> {
+    /// Create a chromatic pitch output without calibration and glide
+    pub fn new(chan: CH) -> Self {
+        PitchChannel {
+            chan,
+            calibration: Calibration::default(),
+            scale: Scale::Chromatic,
+            transpose: 0,
+            glide: 0,
+            current: None,
+            target: 0,
+        }
+    }
+
+    /// Set the calibration of the channel
+    pub fn with_calibration(mut self, calibration: Calibration) -> Self {
+        self.calibration = calibration;
+        self
+    }
+
+    /// Set the scale notes are quantised to
+    pub fn with_scale(mut self, scale: Scale) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// Set the glide rate in microvolts per tick, 0 disables glide
+    pub fn with_glide(mut self, uv_per_tick: u32) -> Self {
+        self.glide = uv_per_tick;
+        self
+    }
+
+    /// Transpose the following notes by a number of semitones. Transposition
+    /// is applied after quantisation, so the scale moves along.
+    pub fn set_transpose(&mut self, semitones: i16) {
+        self.transpose = semitones;
+    }
+
+    /// Quantise, transpose and calibrate a note and glide towards it
+    pub fn note_on(&mut self, note: i16) {
+        let note = self.scale.quantize(note).saturating_add(self.transpose);
+        self.target = self.calibration.note_to_microvolts(note);
+    }
+
+    /// The voltage the output is gliding towards, in microvolts
+    pub fn target_microvolts(&self) -> i32 {
+        self.target
+    }
+
+    /// Returns true while the output has not reached the target voltage
+    pub fn is_gliding(&self) -> bool {
+        self.current != Some(self.target)
+    }
+
+    /// Advance the glide by one tick and write the output if it changed
+    pub fn tick<DEV, IC, E>(&mut self, dac: &mut Ad57xxShared<DEV, IC>) -> Result<(), Error<E>>
+    where
+        DEV: SpiDevice<Error = E>,
+        IC: Sealed<CH = CH>,
+    {
+        let next = match self.current {
+            Some(current) if current == self.target => return Ok(()),
+            Some(current) if self.glide > 0 => {
+                let step = self.glide.min(current.abs_diff(self.target)) as i32;
+                if current < self.target {
+                    current + step
+                } else {
+                    current - step
+                }
+            }
+            _ => self.target,
+        };
+        dac.set_dac_microvolts(self.chan, next)?;
+        self.current = Some(next);
+        Ok(())
+    }
+}
+
+/// A gate or trigger output
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct GateChannel<CH> {
+    chan: CH,
+    low: i32,
+    high: i32,
+    trigger: u32,
+    remaining: u32,
+}
+
+impl<CH: Copy> GateChannel<CH> {
+    /// Create a 0V/5V gate output with 1 tick triggers
+    pub fn new(chan: CH) -> Self {
+        GateChannel {
+            chan,
+            low: 0,
+            high: 5_000_000,
+            trigger: 1,
+            remaining: 0,
+        }
+    }
+
+    /// Set the low and high level in microvolts
+    pub fn with_levels(mut self, low_uv: i32, high_uv: i32) -> Self {
+        self.low = low_uv;
+        self.high = high_uv;
+        self
+    }
+
+    /// Set the length of a trigger in ticks
+    pub fn with_trigger_length(mut self, ticks: u32) -> Self {
+        self.trigger = ticks;
+        self
+    }
+
+    /// Open or close the gate
+    pub fn set<DEV, IC, E>(
+        &mut self,
+        dac: &mut Ad57xxShared<DEV, IC>,
+        open: bool,
+    ) -> Result<(), Error<E>>
+    where
+        DEV: SpiDevice<Error = E>,
+        IC: Sealed<CH = CH>,
+    {
+        self.remaining = 0;
+        let level = if open { self.high } else { self.low };
+        dac.set_dac_microvolts(self.chan, level)
+    }
+
+    /// Open the gate for the trigger length
+    pub fn trigger<DEV, IC, E>(&mut self, dac: &mut Ad57xxShared<DEV, IC>) -> Result<(), Error<E>>
+    where
+        DEV: SpiDevice<Error = E>,
+        IC: Sealed<CH = CH>,
+    {
+        self.set(dac, true)?;
+        self.remaining = self.trigger;
+        Ok(())
+    }
+
+    /// Advance a running trigger by one tick, closing the gate when it ends
+    pub fn tick<DEV, IC, E>(&mut self, dac: &mut Ad57xxShared<DEV, IC>) -> Result<(), Error<E>>
+    where
+        DEV: SpiDevice<Error = E>,
+        IC: Sealed<CH = CH>,
+    {
+        if self.remaining == 0 {
+            return Ok(());
+        }
+        self.remaining -= 1;
+        if self.remaining == 0 {
+            dac.set_dac_microvolts(self.chan, self.low)?;
+        }
+        Ok(())
+    }
+}
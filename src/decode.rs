@@ -0,0 +1,174 @@
+//! Human readable description of frames and register contents
+//!
+//! The command line tool and the trace tests describe frames with the types
+//! of this module, they implement [`Display`] and need no allocation.
+//!
+//! ```ignore
+//! let text = DecodedFrame::dual([0x02, 0x80, 0x00]).to_string();
+//! assert_eq!(text, "write dac register, dac b: 0x8000");
+//! ```
+use core::fmt::{Display, Formatter, Result};
+
+use crate::{marker, private::Sealed, Function, OutputRange};
+
+/// A 24 bit input shift register frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodedFrame {
+    frame: [u8; 3],
+    addresses: &'static [u8],
+}
+
+impl DecodedFrame {
+    /// Frame sent to a quad channel device
+    pub fn quad(frame: [u8; 3]) -> Self {
+        DecodedFrame {
+            frame,
+            addresses: marker::Ad57x4::ADDRESSES,
+        }
+    }
+
+    /// Frame sent to a dual channel device
+    pub fn dual(frame: [u8; 3]) -> Self {
+        DecodedFrame {
+            frame,
+            addresses: marker::Ad57x2::ADDRESSES,
+        }
+    }
+}
+
+impl Display for DecodedFrame {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        let [cmd, hi, lo] = self.frame;
+        let data = (hi as u16) << 8 | lo as u16;
+        let read = cmd & 0x80 != 0;
+        let op = if read { "read" } else { "write" };
+        let reg = (cmd >> 3) & 0b111;
+        let addr = cmd & 0b111;
+        let chan = Channel {
+            addr,
+            addresses: self.addresses,
+        };
+        match reg {
+            0b000 => write!(f, "{op} dac register, {chan}")?,
+            0b001 => write!(f, "{op} range select register, {chan}")?,
+            0b010 => write!(f, "{op} power control register")?,
+            0b011 if addr == Function::Nop as u8 => f.write_str("nop")?,
+            0b011 if addr == Function::Config as u8 => write!(f, "{op} configuration")?,
+            0b011 if addr == Function::Clear as u8 => f.write_str("clear")?,
+            0b011 if addr == Function::Load as u8 => f.write_str("load")?,
+            0b011 => write!(f, "invalid control function {addr}")?,
+            reg => write!(f, "invalid register {reg}")?,
+        }
+        if !read {
+            match reg {
+                0b000 => write!(f, ": 0x{data:04X}")?,
+                0b001 => write!(f, ": {}", OutputRange::from(data & 0b111))?,
+                0b010 => write!(
+                    f,
+                    ": {}",
+                    DecodedPower {
+                        pcfg: data,
+                        addresses: self.addresses
+                    }
+                )?,
+                0b011 if addr == Function::Config as u8 => {
+                    write!(f, ": {}", DecodedConfig(data as u8 & 0xF))?
+                }
+                _ => {}
+            }
+        }
+        if cmd & 0x40 != 0 {
+            f.write_str(" (reserved bit set)")?;
+        }
+        Ok(())
+    }
+}
+
+/// Power control register contents
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodedPower {
+    pcfg: u16,
+    addresses: &'static [u8],
+}
+
+impl DecodedPower {
+    /// Power control register of a quad channel device
+    pub fn quad(pcfg: u16) -> Self {
+        DecodedPower {
+            pcfg,
+            addresses: marker::Ad57x4::ADDRESSES,
+        }
+    }
+
+    /// Power control register of a dual channel device
+    pub fn dual(pcfg: u16) -> Self {
+        DecodedPower {
+            pcfg,
+            addresses: marker::Ad57x2::ADDRESSES,
+        }
+    }
+
+    /// Names of the channels with their bit at `offset` set
+    fn channels(&self, f: &mut Formatter<'_>, offset: u8) -> Result {
+        let mut sep = "";
+        f.write_str("[")?;
+        for (i, addr) in self.addresses.iter().enumerate() {
+            if self.pcfg & 1 << (addr + offset) != 0 {
+                write!(f, "{sep}{}", (b'a' + i as u8) as char)?;
+                sep = " ";
+            }
+        }
+        f.write_str("]")
+    }
+}
+
+impl Display for DecodedPower {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        f.write_str("up: ")?;
+        self.channels(f, 0)?;
+        if self.pcfg & 1 << 5 != 0 {
+            f.write_str(" thermal shutdown")?;
+        }
+        let overcurrent = self.addresses.iter().any(|a| self.pcfg & 1 << (a + 7) != 0);
+        if overcurrent {
+            f.write_str(" overcurrent: ")?;
+            self.channels(f, 7)?;
+        }
+        Ok(())
+    }
+}
+
+/// Control register configuration bits
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodedConfig(pub u8);
+
+impl Display for DecodedConfig {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        let names = ["sdo_disable", "clr_select", "clamp_enable", "tsd_enable"];
+        let mut sep = "";
+        f.write_str("[")?;
+        for (i, name) in names.iter().enumerate() {
+            if self.0 & 1 << i != 0 {
+                write!(f, "{sep}{name}")?;
+                sep = " ";
+            }
+        }
+        f.write_str("]")
+    }
+}
+
+/// Channel selected by an address
+struct Channel {
+    addr: u8,
+    addresses: &'static [u8],
+}
+
+impl Display for Channel {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self.addresses.iter().position(|&a| a == self.addr) {
+            Some(i) => write!(f, "dac {}", (b'a' + i as u8) as char),
+            None if self.addr == 4 => f.write_str("all dacs"),
+            None => write!(f, "invalid address {}", self.addr),
+        }
+    }
+}
@@ -0,0 +1,144 @@
+//! Synchronised update of several devices sharing one ~LDAC line
+//!
+//! With ~LDAC held high writes only change the DAC registers. An
+//! [`LdacGroup`] stages new codes on all devices first and then pulses the
+//! shared ~LDAC line once, so every output changes at the same moment.
+//! Nothing is committed when staging fails on any of the devices.
+//!
+//! ```ignore
+//! let mut group = LdacGroup::new([Chip::from(dac0), Chip::from(dac1)], ldac_pin)?;
+//! group.update(&[
+//!     Staged::new(0, 0, 0x8000),
+//!     Staged::new(1, 1, 0x4000),
+//! ])?;
+//! ```
+use embedded_hal::digital::OutputPin;
+use embedded_hal::spi::SpiDevice;
+
+use crate::mapper::Chip;
+use crate::{marker, private::Sealed, Error};
+
+/// A code to stage on one channel of a device in the group
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Staged {
+    /// Index of the device in the group
+    pub chip: usize,
+    /// Channel index within the device, 0 is channel A
+    pub channel: usize,
+    /// DAC register value
+    pub code: u16,
+}
+
+impl Staged {
+    /// Create a new staged update
+    pub fn new(chip: usize, channel: usize, code: u16) -> Self {
+        Staged {
+            chip,
+            channel,
+            code,
+        }
+    }
+}
+
+/// Errors of a group update
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum GroupError<E, P> {
+    /// Staging failed on one or more devices, nothing was committed.
+    ///
+    /// The devices that did not fail hold the new codes in their DAC
+    /// registers, these are applied by the next commit or load operation.
+    Staging {
+        /// Bitmask of the devices on which staging failed
+        failed: u32,
+        /// Index of the first device on which staging failed
+        chip: usize,
+        /// Error of the first failed staging write
+        error: Error<E>,
+    },
+    /// Driving the ~LDAC pin failed
+    Ldac(P),
+}
+
+/// Devices sharing one ~LDAC line
+pub struct LdacGroup<DEV, LDAC, const N: usize> {
+    chips: [Chip<DEV>; N],
+    ldac: LDAC,
+}
+
+impl<DEV, E, LDAC, P, const N: usize> LdacGroup<DEV, LDAC, N>
+where
+    DEV: SpiDevice<Error = E>,
+    LDAC: OutputPin<Error = P>,
+{
+    /// Create a group and drive ~LDAC high. At most 32 devices are supported,
+    /// larger groups fail to compile.
+    pub fn new(chips: [Chip<DEV>; N], mut ldac: LDAC) -> Result<Self, P> {
+        const { assert!(N <= 32, "an LdacGroup supports at most 32 devices") };
+        ldac.set_high()?;
+        Ok(LdacGroup { chips, ldac })
+    }
+
+    /// Return the devices and the ~LDAC pin
+    pub fn destroy(self) -> ([Chip<DEV>; N], LDAC) {
+        (self.chips, self.ldac)
+    }
+
+    /// Access a device
+    pub fn chip_mut(&mut self, index: usize) -> Option<&mut Chip<DEV>> {
+        self.chips.get_mut(index)
+    }
+
+    /// Write the staged codes to the DAC registers without updating the
+    /// outputs. Staging continues on the other devices after a failure so
+    /// every failed device is reported.
+    pub fn stage(&mut self, updates: &[Staged]) -> Result<(), GroupError<E, P>> {
+        let mut failed = 0u32;
+        let mut first = None;
+        for update in updates {
+            let bit = 1u32.checked_shl(update.chip as u32).unwrap_or(0);
+            if failed & bit != 0 {
+                continue;
+            }
+            if let Err(error) = self.stage_one(update) {
+                failed |= bit;
+                first.get_or_insert((update.chip, error));
+            }
+        }
+        match first {
+            Some((chip, error)) => Err(GroupError::Staging {
+                failed,
+                chip,
+                error,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Pulse ~LDAC to update the outputs of all devices at once
+    pub fn commit(&mut self) -> Result<(), GroupError<E, P>> {
+        self.ldac.set_low().map_err(GroupError::Ldac)?;
+        self.ldac.set_high().map_err(GroupError::Ldac)
+    }
+
+    /// Stage the updates and commit them if staging succeeded on every device
+    pub fn update(&mut self, updates: &[Staged]) -> Result<(), GroupError<E, P>> {
+        self.stage(updates)?;
+        self.commit()
+    }
+
+    fn stage_one(&mut self, update: &Staged) -> Result<(), Error<E>> {
+        match self.chips.get_mut(update.chip) {
+            Some(Chip::Quad(dac)) => {
+                let chan = marker::Ad57x4::CHANNELS.get(update.channel);
+                dac.set_dac_output(*chan.ok_or(Error::InvalidArgument)?, update.code)
+            }
+            Some(Chip::Dual(dac)) => {
+                let chan = marker::Ad57x2::CHANNELS.get(update.channel);
+                dac.set_dac_output(*chan.ok_or(Error::InvalidArgument)?, update.code)
+            }
+            None => Err(Error::InvalidArgument),
+        }
+    }
+}
@@ -5,12 +5,25 @@
 use bitfield_struct::bitfield;
 use core::include_str;
 use core::marker::PhantomData;
+use embedded_hal::spi::{Operation, SpiDevice};
+
+use coding::Coding;
+use register::{Control, DacReg, PowerReg, RangeReg, Readable, Register};
 
 /// AD57xx DAC with shared SPI bus access
 pub struct Ad57xxShared<DEV, IC> {
     spi: DEV,
     cfg: Config,
     pcfg: u16,
+    // Last written range and DAC register value, indexed by channel address
+    ranges: [OutputRange; 4],
+    codes: [u16; 4],
+    coding: Coding,
+    // Reference voltage in microvolts
+    vref: u32,
+    // Position of the integrity scrubber in the register sequence
+    #[cfg_attr(not(feature = "readback"), allow(dead_code))]
+    scrub_pos: usize,
     _ic: PhantomData<IC>,
 }
 
@@ -20,6 +33,11 @@ impl<DEV, IC> Ad57xxShared<DEV, IC> {
             spi,
             cfg: Config::default(),
             pcfg: 0,
+            ranges: [OutputRange::Unipolar5V; 4],
+            codes: [0; 4],
+            coding: Coding::OffsetBinary,
+            vref: voltage::VREF_DEFAULT,
+            scrub_pos: 0,
             _ic: PhantomData,
         }
     }
@@ -29,9 +47,104 @@ impl<DEV, IC> Ad57xxShared<DEV, IC> {
     }
 }
 
+impl<DEV, IC, E> Ad57xxShared<DEV, IC>
+where
+    DEV: SpiDevice<Error = E>,
+    IC: private::Sealed,
+{
+    /// Write a 24bit frame and track its effect on the driver state
+    pub(crate) fn write_frame(&mut self, payload: &[u8; 3]) -> Result<(), Error<E>> {
+        self.spi
+            .transaction(&mut [Operation::Write(payload)])
+            .map_err(Error::Spi)?;
+        self.track(payload);
+        Ok(())
+    }
+
+    /// Read back the 16bit data of the register addressed by `cmd`
+    pub(crate) fn read_frame(&mut self, cmd: u8) -> Result<u16, Error<E>> {
+        self.spi.write(&[cmd, 0, 0]).map_err(Error::Spi)?;
+        let mut rx: [u8; 3] = [0x00; 3];
+        // Send a NOP instruction (0x18) while reading, the device shifts out
+        // the addressed register as [header, MSB, LSB]
+        self.spi
+            .transfer(&mut rx, &[0x18, 0, 0])
+            .map_err(Error::Spi)?;
+        Ok(((rx[1] as u16) << 8) | rx[2] as u16)
+    }
+}
 
+impl<DEV, IC: private::Sealed> Ad57xxShared<DEV, IC> {
+    /// Update the cached register contents after a successful write
+    pub(crate) fn track(&mut self, payload: &[u8; 3]) {
+        let cmd = CommandByte::from(payload[0]);
+        let data = ((payload[1] as u16) << 8) | payload[2] as u16;
+        let addr = cmd.addr();
+        match cmd.reg() {
+            0b000 => {
+                for &chan in IC::ADDRESSES.iter().filter(|&&a| addr == 4 || a == addr) {
+                    self.codes[chan as usize] = data;
+                }
+            }
+            0b001 => {
+                for &chan in IC::ADDRESSES.iter().filter(|&&a| addr == 4 || a == addr) {
+                    self.ranges[chan as usize] = OutputRange::from(data & 0b111);
+                }
+            }
+            0b010 => self.pcfg = data,
+            _ if addr == Function::Config as u8 => self.cfg = Config::from(data as u8),
+            _ if addr == Function::Clear as u8 => {
+                for &chan in IC::ADDRESSES {
+                    self.codes[chan as usize] = self.clear_code(self.ranges[chan as usize]);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// DAC register contents after a clear operation
+    fn clear_code(&self, range: OutputRange) -> u16 {
+        let code = match (range.is_bipolar(), self.cfg.clr_select()) {
+            (false, false) | (true, true) => 0x0000,
+            (false, true) | (true, false) => 0x8000,
+        };
+        self.coding.offset_binary(range, code)
+    }
+
+    /// Power control frame powering the channel(s) at `addr` up or down
+    pub(crate) fn power_frame(&self, addr: u8, pwr: bool) -> [u8; 3] {
+        let mask = match addr {
+            4 => IC::PU_MASK,
+            a => 1 << a,
+        };
+        let pcfg = self.pcfg & IC::PU_MASK;
+        frame(0b010, 0, if pwr { pcfg | mask } else { pcfg & !mask })
+    }
+
+    /// DAC register frames for an output voltage of the channel(s) at `addr`,
+    /// one per selected channel. The codes are rounded to a resolution of
+    /// `bits`. `None` if the voltage is outside the range of any of them, so
+    /// nothing has to be written before a later channel is rejected.
+    pub(crate) fn microvolts_frames(
+        &self,
+        addr: u8,
+        uv: i32,
+        bits: u8,
+    ) -> Option<[Option<[u8; 3]>; 4]> {
+        let shift = 16 - bits;
+        let half = (1u32 << shift) >> 1;
+        let mut frames = [None; 4];
+        let selected = IC::ADDRESSES.iter().filter(|&&a| addr == 4 || a == addr);
+        for (slot, &a) in frames.iter_mut().zip(selected) {
+            let range = self.ranges[a as usize];
+            let code = range.microvolts_to_code(uv, self.vref)?;
+            let code = (((code as u32 + half) >> shift).min(0xFFFF >> shift) as u16) << shift;
+            *slot = Some(frame(0b000, a, self.coding.offset_binary(range, code)));
+        }
+        Some(frames)
+    }
+}
 
-trait Ad57xxPrivate {}
 
 /// Common functionality among the Ad57xx range
 pub trait Ad57xx<DEV, E> where 
@@ -58,12 +171,12 @@ u8:  From<Command::<<Self as Ad57xx<DEV, E>>::CH>>,
     /// and ~SYNC pins or through the load function in the control register.
     /// The actual output voltage will depend on the reference voltage, output
     /// range and for bipolar ranges on the state of the BIN/~2sCOMPLEMENT pin.
-    /// ```
+    /// ```ignore
     /// ad5754.set_dac_output(Channel::DacA, 0x8000);
     /// ```
     ///
     fn set_dac_output(&mut self, chan: Self::CH, val: u16) -> Result<(), Error<E>> {
-        self.write(Command::DacRegister(chan), Data::DacValue(val))
+        self.write_register(DacReg(chan), val)
     }
 
     /// Set the device configuration
@@ -80,111 +193,78 @@ u8:  From<Command::<<Self as Ad57xx<DEV, E>>::CH>>,
 
     /// Set the output range of the selected DAC channel
     fn set_output_range(&mut self, chan: Self::CH, range: OutputRange) -> Result<(), Error<E>> {
-        self.write(Command::RangeSelectRegister(chan), Data::OutputRange(range))
+        self.write_register(RangeReg(chan), range)
     }
     /// This function sets the DAC registers to the clear code and updates the outputs.
     fn clear_dacs(&mut self) -> Result<(), Error<E>> {
-        self.write(Command::<Self::CH>::ControlRegister(Function::Clear), Data::None)
+        self.write_register(Control::<register::Clear>::new(), ())
     }
     /// This function updates the DAC registers and, consequently, the DAC outputs.
     fn load_dacs(&mut self) -> Result<(), Error<E>> {
-        self.write(Command::<Self::CH>::ControlRegister(Function::Load), Data::None)
+        self.write_register(Control::<register::Load>::new(), ())
+    }
+
+    /// Write a value to a register, see [`register`]
+    ///
+    /// Returns [`Error::InvalidArgument`] for a value the register can not
+    /// hold, such as [`OutputRange::InvalidReadback`].
+    fn write_register<R>(&mut self, reg: R, val: R::Value) -> Result<(), Error<E>>
+    where
+        R: Register<Self::CH, Self::PCFG>,
+    {
+        let (r, addr) = reg.address();
+        let data = R::encode(val).ok_or(Error::InvalidArgument)?;
+        self.spi_write(&frame(r, addr, data))
+    }
+
+    /// Read back the value of a register, see [`register`]
+    fn read_register<R>(&mut self, reg: R) -> Result<R::Value, Error<E>>
+    where
+        R: Readable<Self::CH, Self::PCFG>,
+    {
+        let (r, addr) = reg.address();
+        let data = self.spi_read(read_cmd(r, addr))?;
+        Ok(R::decode(data))
     }
 
     /// Write data to the device
     fn write(&mut self, cmd: Command<Self::CH>, data: Data<Self::PCFG>) -> Result<(), Error<E>> {
-        let payload: [u8; 3] = match cmd {
-            Command::DacRegister(addr) => {
-                if let Data::DacValue(val) = data {
-                    [
-                        CommandByte::new()
-                            .with_addr(u8::from(addr))
-                            .with_reg(u8::from(cmd))
-                            .into(),
-                        (val >> 8) as u8,
-                        val as u8,
-                    ]
-                } else {
-                    return Err(Error::InvalidArgument);
-                }
+        match (cmd, data) {
+            (Command::DacRegister(chan), Data::DacValue(val)) => {
+                self.write_register(DacReg(chan), val)
             }
-            Command::RangeSelectRegister(addr) => {
-                if let Data::OutputRange(val) = data {
-                    [
-                        CommandByte::new()
-                            .with_addr(u8::from(addr))
-                            .with_reg(u8::from(cmd))
-                            .into(),
-                        0x00,
-                        val as u8,
-                    ]
-                } else {
-                    return Err(Error::InvalidArgument);
-                }
+            (Command::RangeSelectRegister(chan), Data::OutputRange(range)) => {
+                self.write_register(RangeReg(chan), range)
             }
-            Command::PowerControlRegister => {
-                if let Data::PowerControl(pcfg) = data {
-                    [
-                        CommandByte::new().with_reg(u8::from(cmd)).into(),
-                        (u16::from(pcfg) >> 8) as u8,
-                        (u16::from(pcfg)) as u8,
-                    ]
-                } else {
-                    return Err(Error::InvalidArgument);
-                }
+            (Command::PowerControlRegister, Data::PowerControl(pcfg)) => {
+                self.write_register(PowerReg, pcfg)
             }
-            Command::ControlRegister(func) if func == Function::Config => {
-                if let Data::Control(cfg) = data {
-                    [
-                        CommandByte::new()
-                            .with_reg(u8::from(cmd))
-                            .with_addr(func as u8)
-                            .into(),
-                        0x00,
-                        u8::from(cfg) as u8,
-                    ]
-                } else {
-                    return Err(Error::InvalidArgument);
-                }
+            (Command::ControlRegister(Function::Config), Data::Control(cfg)) => {
+                self.write_register(Control::<Config>::new(), cfg)
             }
-            Command::ControlRegister(func) => {
-                if let Data::None = data {
-                    [
-                        CommandByte::new()
-                            .with_reg(u8::from(cmd))
-                            .with_addr(func as u8)
-                            .into(),
-                        0x00,
-                        0x00,
-                    ]
-                } else {
-                    return Err(Error::InvalidArgument);
-                }
+            (Command::ControlRegister(Function::Nop), Data::None) => {
+                self.write_register(Control::<register::Nop>::new(), ())
             }
-        };
-        self.spi_write(&payload)
+            (Command::ControlRegister(Function::Clear), Data::None) => {
+                self.write_register(Control::<register::Clear>::new(), ())
+            }
+            (Command::ControlRegister(Function::Load), Data::None) => {
+                self.write_register(Control::<register::Load>::new(), ())
+            }
+            _ => Err(Error::InvalidArgument),
+        }
     }
 
     /// Read data from the device
     fn read(&mut self, cmd: Command<Self::CH>) -> Result<Data<Self::PCFG>, Error<E>> {
-        let addr = match cmd {
-            Command::DacRegister(addr) => u8::from(addr),
-            Command::RangeSelectRegister(addr) => u8::from(addr),
-            Command::PowerControlRegister => 0,
-            Command::ControlRegister(function) => function as u8,
-        };
-        // The register to be read with the read bit set
-        let cmd_byte = CommandByte::new()
-            .with_rw(true)
-            .with_reg(u8::from(cmd))
-            .with_addr(addr);
-        let data = self.spi_read(u8::from(cmd_byte))?;
         match cmd {
-            Command::DacRegister(_) => Ok(Data::DacValue(data)),
-            Command::RangeSelectRegister(_) => Ok(Data::OutputRange(OutputRange::from(data))),
-            Command::PowerControlRegister => Ok(Data::PowerControl(data.into())),
-            Command::ControlRegister(func) if func == Function::Config => {
-                Ok(Data::Control(Config::from(data as u8)))
+            Command::DacRegister(chan) => self.read_register(DacReg(chan)).map(Data::DacValue),
+            Command::RangeSelectRegister(chan) => {
+                self.read_register(RangeReg(chan)).map(Data::OutputRange)
+            }
+            Command::PowerControlRegister => self.read_register(PowerReg).map(Data::PowerControl),
+            Command::ControlRegister(Function::Config) => {
+                self.read_register(Control::<Config>::new()).map(Data::Control)
             }
             Command::ControlRegister(_) => Err(Error::ReadError),
         }
@@ -193,6 +273,7 @@ u8:  From<Command::<<Self as Ad57xx<DEV, E>>::CH>>,
 
 /// Errors for this crate
 #[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Error<E> {
     /// SPI communication error
     Spi(E),
@@ -202,8 +283,30 @@ pub enum Error<E> {
     ReadError,
 }
 
+impl<E: core::fmt::Debug> core::fmt::Display for Error<E> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Error::Spi(e) => write!(f, "SPI communication error: {:?}", e),
+            Error::InvalidArgument => f.write_str("invalid argument"),
+            Error::ReadError => f.write_str("invalid readback data"),
+        }
+    }
+}
+
+impl<E: core::fmt::Debug> core::error::Error for Error<E> {}
+
+impl<E: embedded_hal::spi::Error> embedded_hal::spi::Error for Error<E> {
+    fn kind(&self) -> embedded_hal::spi::ErrorKind {
+        match self {
+            Error::Spi(e) => e.kind(),
+            _ => embedded_hal::spi::ErrorKind::Other,
+        }
+    }
+}
+
 /// Data to send to this device
 #[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Data<PCFG> {
     /// A dac value
     DacValue(u16),
@@ -218,6 +321,7 @@ pub enum Data<PCFG> {
 }
 /// Enum determining the contents of the Register and Address bits
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[repr(u8)]
 pub enum Command<C> {
     /// Access the DAC register of the channel(s)
@@ -242,6 +346,7 @@ impl<C> From<Command<C>> for u8 {
 
 /// Address of a function in the control register
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[repr(u8)]
 pub enum Function {
     /// No-operation function used for readback operations
@@ -258,7 +363,9 @@ pub enum Function {
 /// These values are valid with a reference input of 2.5V, if the reference
 /// voltage is different, consult the datasheet for the gains associated with
 /// these settings.
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u16)]
 pub enum OutputRange {
     /// Gain = 2, 0V to +5V when Vref = 2.5V
@@ -274,6 +381,7 @@ pub enum OutputRange {
     /// Gain = 8.64, -10.8 to +10.8V when Vref = 2.5V
     Bipolar10_8V = 0b101,
     /// Invalid readback result
+    #[cfg_attr(feature = "serde", serde(skip))]
     InvalidReadback,
 }
 impl From<u16> for OutputRange {
@@ -289,6 +397,83 @@ impl From<u16> for OutputRange {
         }
     }
 }
+impl core::fmt::Display for OutputRange {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(match self {
+            Self::Unipolar5V => "0V to +5V",
+            Self::Unipolar10V => "0V to +10V",
+            Self::Unipolar10_8V => "0V to +10.8V",
+            Self::Bipolar5V => "-5V to +5V",
+            Self::Bipolar10V => "-10V to +10V",
+            Self::Bipolar10_8V => "-10.8V to +10.8V",
+            Self::InvalidReadback => "invalid range",
+        })
+    }
+}
+
+impl OutputRange {
+    /// Returns true for the ranges that span negative and positive voltages
+    pub fn is_bipolar(&self) -> bool {
+        matches!(
+            self,
+            Self::Bipolar5V | Self::Bipolar10V | Self::Bipolar10_8V
+        )
+    }
+
+    /// Full scale span as a multiple of the reference voltage, in hundredths.
+    /// Returns 0 for [`OutputRange::InvalidReadback`].
+    pub fn span(&self) -> u32 {
+        match self {
+            Self::Unipolar5V => 200,
+            Self::Unipolar10V => 400,
+            Self::Unipolar10_8V => 432,
+            Self::Bipolar5V => 400,
+            Self::Bipolar10V => 800,
+            Self::Bipolar10_8V => 864,
+            Self::InvalidReadback => 0,
+        }
+    }
+
+    /// Output level of an offset binary `code` relative to the reference
+    /// voltage, scaled by 100 * 2^16.
+    pub(crate) fn level(&self, code: u16) -> i64 {
+        let span = self.span() as i64;
+        let offset = if self.is_bipolar() { span << 15 } else { 0 };
+        span * code as i64 - offset
+    }
+
+    /// Offset binary code closest to `level`, clamped to the range.
+    /// Returns `None` for [`OutputRange::InvalidReadback`].
+    pub(crate) fn code(&self, level: i64) -> Option<u16> {
+        self.unclamped_code(level)
+            .map(|code| code.clamp(0, 0xFFFF) as u16)
+    }
+
+    /// Offset binary code closest to `level`, without clamping to the range
+    pub(crate) fn unclamped_code(&self, level: i64) -> Option<i64> {
+        let span = self.span() as i64;
+        if span == 0 {
+            return None;
+        }
+        let offset = if self.is_bipolar() { span << 15 } else { 0 };
+        Some((level + offset + span / 2).div_euclid(span))
+    }
+}
+
+/// Encode a write frame for the given register, address and data
+pub(crate) fn frame(reg: u8, addr: u8, data: u16) -> [u8; 3] {
+    let cmd = CommandByte::new().with_reg(reg).with_addr(addr);
+    [cmd.into(), (data >> 8) as u8, data as u8]
+}
+
+/// Encode the command byte reading back the given register and address
+pub(crate) fn read_cmd(reg: u8, addr: u8) -> u8 {
+    CommandByte::new()
+        .with_rw(true)
+        .with_reg(reg)
+        .with_addr(addr)
+        .into()
+}
 
 #[bitfield(u8)]
 struct CommandByte {
@@ -307,6 +492,7 @@ struct CommandByte {
 
 /// Definition of the configuration in the Control Register
 #[bitfield(u8)]
+#[derive(PartialEq, Eq)]
 pub struct Config {
     /// Set by the user to disable the SDO output. Cleared by the user to
     /// enable the SDO output (default).
@@ -314,7 +500,7 @@ pub struct Config {
     pub sdo_disable: bool,
 
     /// Sets the output voltage after a clear operation.
-    /// ```
+    /// ```text
     /// | CLR_Select | Unipolar | Bipolar Operation   |
     /// |------------|----------|---------------------|
     /// | 0          | 0V       | 0V                  |
@@ -336,6 +522,20 @@ pub struct Config {
     _unused: u16,
 }
 
+#[cfg(feature = "defmt")]
+impl defmt::Format for Config {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(
+            f,
+            "Config {{ sdo_disable: {}, clr_select: {}, clamp_enable: {}, tsd_enable: {} }}",
+            self.sdo_disable(),
+            self.clr_select(),
+            self.clamp_enable(),
+            self.tsd_enable()
+        )
+    }
+}
+
 /// Markers
 #[doc(hidden)]
 pub mod marker {
@@ -345,11 +545,66 @@ pub mod marker {
 
 pub mod ad57x2;
 pub mod ad57x4;
+pub mod any;
+pub mod asynch;
+pub mod channels;
+pub mod coding;
+pub mod cv;
+pub mod decode;
+pub mod group;
+pub mod mapper;
+pub mod model;
+pub mod modulation;
+pub mod range;
+pub mod recovery;
+pub mod register;
+#[cfg(feature = "readback")]
+pub mod scrub;
+#[cfg(feature = "readback")]
+pub mod selftest;
+pub mod sequence;
+#[cfg(feature = "critical-section")]
+pub mod shared;
+#[cfg(feature = "embassy-sync")]
+pub mod shared_async;
+pub mod shutdown;
+#[cfg(feature = "serde")]
+mod serialize;
+pub mod state;
+pub mod stream;
+pub mod voltage;
+pub mod watchdog;
 
 mod private {
     use super::marker;
-    pub trait Sealed {}
+    use super::ad57x2::ChannelDual;
+    use super::ad57x4::ChannelQuad;
+    pub trait Sealed {
+        /// Channel type of the part
+        type CH: Copy + Into<u8> + 'static;
+        /// The individually addressable channels
+        const CHANNELS: &'static [Self::CH];
+        /// Addresses of the individual channels
+        const ADDRESSES: &'static [u8];
+        /// Power-up bits in the power control register
+        const PU_MASK: u16;
+    }
 
-    impl Sealed for marker::Ad57x4 {}
-    impl Sealed for marker::Ad57x2 {}
+    impl Sealed for marker::Ad57x4 {
+        type CH = ChannelQuad;
+        const CHANNELS: &'static [ChannelQuad] = &[
+            ChannelQuad::DacA,
+            ChannelQuad::DacB,
+            ChannelQuad::DacC,
+            ChannelQuad::DacD,
+        ];
+        const ADDRESSES: &'static [u8] = &[0, 1, 2, 3];
+        const PU_MASK: u16 = 0b1111;
+    }
+    impl Sealed for marker::Ad57x2 {
+        type CH = ChannelDual;
+        const CHANNELS: &'static [ChannelDual] = &[ChannelDual::DacA, ChannelDual::DacB];
+        const ADDRESSES: &'static [u8] = &[0, 2];
+        const PU_MASK: u16 = 0b0101;
+    }
 }
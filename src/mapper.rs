@@ -0,0 +1,264 @@
+//! Logical channel numbering across several devices
+//!
+//! A [`ChannelMap`] owns a set of quad and dual channel devices and numbers
+//! their channels consecutively, in the order the devices are given. Each
+//! logical channel carries [`ChannelInfo`] metadata with its range and
+//! limits.
+//!
+//! ```ignore
+//! let mut map = ChannelMap::new(
+//!     [
+//!         Chip::from(Ad57xxShared::new_ad57x4(dev0)),
+//!         Chip::from(Ad57xxShared::new_ad57x2(dev1)),
+//!     ],
+//!     [ChannelInfo::new("cv", OutputRange::Bipolar10V); 6],
+//! )
+//! .ok()
+//! .unwrap();
+//! map.apply_ranges()?;
+//! map.set_power_all(true)?;
+//! map.set_microvolts(5, 1_000_000)?; // DAC B of the AD57x2
+//! ```
+use embedded_hal::spi::SpiDevice;
+
+use crate::ad57x2::ChannelDual;
+use crate::ad57x4::ChannelQuad;
+use crate::{marker, private::Sealed, Ad57xx, Ad57xxShared, Config, Error, OutputRange};
+
+/// A quad or dual channel device
+pub enum Chip<DEV> {
+    /// Quad channel device
+    Quad(Ad57xxShared<DEV, marker::Ad57x4>),
+    /// Dual channel device
+    Dual(Ad57xxShared<DEV, marker::Ad57x2>),
+}
+
+impl<DEV> From<Ad57xxShared<DEV, marker::Ad57x4>> for Chip<DEV> {
+    fn from(dac: Ad57xxShared<DEV, marker::Ad57x4>) -> Self {
+        Chip::Quad(dac)
+    }
+}
+
+impl<DEV> From<Ad57xxShared<DEV, marker::Ad57x2>> for Chip<DEV> {
+    fn from(dac: Ad57xxShared<DEV, marker::Ad57x2>) -> Self {
+        Chip::Dual(dac)
+    }
+}
+
+impl<DEV> Chip<DEV> {
+    /// Number of channels of the device
+    pub fn channel_count(&self) -> usize {
+        match self {
+            Chip::Quad(_) => marker::Ad57x4::CHANNELS.len(),
+            Chip::Dual(_) => marker::Ad57x2::CHANNELS.len(),
+        }
+    }
+}
+
+/// Metadata of a logical channel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ChannelInfo {
+    /// Name of the channel
+    pub name: &'static str,
+    /// Output range written by [`ChannelMap::apply_ranges`]
+    pub range: OutputRange,
+    /// Lowest output voltage accepted, in microvolts
+    pub min_uv: i32,
+    /// Highest output voltage accepted, in microvolts
+    pub max_uv: i32,
+    /// Unit of the quantity the channel represents, for display purposes
+    pub units: &'static str,
+}
+
+impl ChannelInfo {
+    /// Create metadata for a channel in volts, limited only by its range
+    pub fn new(name: &'static str, range: OutputRange) -> Self {
+        ChannelInfo {
+            name,
+            range,
+            min_uv: i32::MIN,
+            max_uv: i32::MAX,
+            units: "V",
+        }
+    }
+
+    /// Restrict the accepted output voltages
+    pub fn with_limits(mut self, min_uv: i32, max_uv: i32) -> Self {
+        self.min_uv = min_uv;
+        self.max_uv = max_uv;
+        self
+    }
+
+    /// Set the unit of the channel
+    pub fn with_units(mut self, units: &'static str) -> Self {
+        self.units = units;
+        self
+    }
+}
+
+/// Position of a logical channel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Location {
+    /// Channel of the quad channel device at the given index
+    Quad(usize, ChannelQuad),
+    /// Channel of the dual channel device at the given index
+    Dual(usize, ChannelDual),
+}
+
+/// Devices with a flat logical channel numbering
+pub struct ChannelMap<DEV, const CHIPS: usize, const CHANNELS: usize> {
+    chips: [Chip<DEV>; CHIPS],
+    info: [ChannelInfo; CHANNELS],
+}
+
+impl<DEV, E, const CHIPS: usize, const CHANNELS: usize> ChannelMap<DEV, CHIPS, CHANNELS>
+where
+    DEV: SpiDevice<Error = E>,
+{
+    /// Create a channel map. The devices are handed back if the number of
+    /// channels of the devices does not match the number of channel
+    /// descriptions.
+    pub fn new(
+        chips: [Chip<DEV>; CHIPS],
+        info: [ChannelInfo; CHANNELS],
+    ) -> Result<Self, [Chip<DEV>; CHIPS]> {
+        let count: usize = chips.iter().map(Chip::channel_count).sum();
+        if count != CHANNELS {
+            return Err(chips);
+        }
+        Ok(ChannelMap { chips, info })
+    }
+
+    /// Return the devices
+    pub fn destroy(self) -> [Chip<DEV>; CHIPS] {
+        self.chips
+    }
+
+    /// Number of logical channels
+    pub fn channel_count(&self) -> usize {
+        CHANNELS
+    }
+
+    /// Metadata of a logical channel
+    pub fn info(&self, chan: usize) -> Option<&ChannelInfo> {
+        self.info.get(chan)
+    }
+
+    /// Find the device and channel of a logical channel
+    pub fn locate(&self, chan: usize) -> Option<Location> {
+        let mut first = 0;
+        for (i, chip) in self.chips.iter().enumerate() {
+            let local = chan.checked_sub(first)?;
+            match chip {
+                Chip::Quad(_) if local < 4 => {
+                    return Some(Location::Quad(i, marker::Ad57x4::CHANNELS[local]))
+                }
+                Chip::Dual(_) if local < 2 => {
+                    return Some(Location::Dual(i, marker::Ad57x2::CHANNELS[local]))
+                }
+                _ => first += chip.channel_count(),
+            }
+        }
+        None
+    }
+
+    /// Access a device
+    pub fn chip_mut(&mut self, index: usize) -> Option<&mut Chip<DEV>> {
+        self.chips.get_mut(index)
+    }
+
+    /// Write a code to the DAC register of a logical channel
+    pub fn set_code(&mut self, chan: usize, code: u16) -> Result<(), Error<E>> {
+        match self.locate(chan).ok_or(Error::InvalidArgument)? {
+            Location::Quad(i, ch) => self.quad(i)?.set_dac_output(ch, code),
+            Location::Dual(i, ch) => self.dual(i)?.set_dac_output(ch, code),
+        }
+    }
+
+    /// Write an output voltage in microvolts to a logical channel.
+    ///
+    /// Returns [`Error::InvalidArgument`] if the voltage is outside the limits
+    /// of the channel or its range.
+    pub fn set_microvolts(&mut self, chan: usize, uv: i32) -> Result<(), Error<E>> {
+        let info = self.info.get(chan).ok_or(Error::InvalidArgument)?;
+        if !(info.min_uv..=info.max_uv).contains(&uv) {
+            return Err(Error::InvalidArgument);
+        }
+        match self.locate(chan).ok_or(Error::InvalidArgument)? {
+            Location::Quad(i, ch) => self.quad(i)?.set_dac_microvolts(ch, uv),
+            Location::Dual(i, ch) => self.dual(i)?.set_dac_microvolts(ch, uv),
+        }
+    }
+
+    /// Write the range of every logical channel as given by its metadata
+    pub fn apply_ranges(&mut self) -> Result<(), Error<E>> {
+        for chan in 0..CHANNELS {
+            let range = self.info[chan].range;
+            match self.locate(chan).ok_or(Error::InvalidArgument)? {
+                Location::Quad(i, ch) => self.quad(i)?.set_output_range(ch, range)?,
+                Location::Dual(i, ch) => self.dual(i)?.set_output_range(ch, range)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Power up or down every channel of every device
+    pub fn set_power_all(&mut self, pwr: bool) -> Result<(), Error<E>> {
+        for chip in self.chips.iter_mut() {
+            match chip {
+                Chip::Quad(dac) => dac.set_power(ChannelQuad::AllDacs, pwr)?,
+                Chip::Dual(dac) => dac.set_power(ChannelDual::AllDacs, pwr)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the same configuration to every device
+    pub fn set_config_all(&mut self, cfg: Config) -> Result<(), Error<E>> {
+        for chip in self.chips.iter_mut() {
+            match chip {
+                Chip::Quad(dac) => dac.set_config(cfg)?,
+                Chip::Dual(dac) => dac.set_config(cfg)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Load the DAC registers of every device
+    pub fn load_all(&mut self) -> Result<(), Error<E>> {
+        for chip in self.chips.iter_mut() {
+            match chip {
+                Chip::Quad(dac) => dac.load_dacs()?,
+                Chip::Dual(dac) => dac.load_dacs()?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Clear the DAC registers of every device
+    pub fn clear_all(&mut self) -> Result<(), Error<E>> {
+        for chip in self.chips.iter_mut() {
+            match chip {
+                Chip::Quad(dac) => dac.clear_dacs()?,
+                Chip::Dual(dac) => dac.clear_dacs()?,
+            }
+        }
+        Ok(())
+    }
+
+    fn quad(&mut self, i: usize) -> Result<&mut Ad57xxShared<DEV, marker::Ad57x4>, Error<E>> {
+        match &mut self.chips[i] {
+            Chip::Quad(dac) => Ok(dac),
+            Chip::Dual(_) => Err(Error::InvalidArgument),
+        }
+    }
+
+    fn dual(&mut self, i: usize) -> Result<&mut Ad57xxShared<DEV, marker::Ad57x2>, Error<E>> {
+        match &mut self.chips[i] {
+            Chip::Dual(dac) => Ok(dac),
+            Chip::Quad(_) => Err(Error::InvalidArgument),
+        }
+    }
+}
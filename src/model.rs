@@ -0,0 +1,230 @@
+//! Software model of an AD57xx device
+//!
+//! [`Model`] implements [`SpiDevice`] and keeps the register file of a quad or
+//! dual channel device, so the driver can be exercised without hardware.
+//! Both the blocking and the asynchronous `SpiDevice` traits are implemented.
+//! Every SPI transaction is one SYNC frame, the last 24 bits written are
+//! latched when the transaction ends. Readback data is shifted out during the
+//! frame following a read command, the read command byte followed by the
+//! register contents MSB first.
+//!
+//! ```ignore
+//! let mut dac = Ad57xxShared::new_ad57x4(Model::ad57x4());
+//! dac.set_power(ChannelQuad::AllDacs, true)?;
+//! dac.set_dac_output(ChannelQuad::DacA, 0x8000)?;
+//! assert_eq!(dac.destroy().output(0), Some(0x8000));
+//! ```
+use core::convert::Infallible;
+use core::marker::PhantomData;
+
+use embedded_hal::spi::{ErrorType, Operation, SpiDevice};
+
+use crate::{marker, private::Sealed, CommandByte, Config, Function, OutputRange};
+
+/// Register file of a simulated device
+#[derive(Debug, Clone)]
+pub struct Model<IC> {
+    dac: [u16; 4],
+    output: [u16; 4],
+    range: [u16; 4],
+    power: u16,
+    config: Config,
+    ldac: bool,
+    shift: [u8; 3],
+    frames: usize,
+    _ic: PhantomData<IC>,
+}
+
+impl Model<marker::Ad57x4> {
+    /// Model of a quad channel device
+    pub fn ad57x4() -> Self {
+        Self::create()
+    }
+}
+
+impl Model<marker::Ad57x2> {
+    /// Model of a dual channel device
+    pub fn ad57x2() -> Self {
+        Self::create()
+    }
+}
+
+impl<IC: Sealed> Model<IC> {
+    fn create() -> Self {
+        Model {
+            dac: [0; 4],
+            output: [0; 4],
+            range: [0; 4],
+            power: 0,
+            config: Config::default(),
+            ldac: false,
+            shift: [0; 3],
+            frames: 0,
+            _ic: PhantomData,
+        }
+    }
+
+    /// Return all registers to their power-on values
+    pub fn reset(&mut self) {
+        let ldac = self.ldac;
+        *self = Self::create();
+        self.ldac = ldac;
+    }
+
+    /// Drive the ~LDAC pin. While it is low (default) the outputs follow the
+    /// DAC registers, while it is high they only change on a load operation.
+    pub fn set_ldac(&mut self, high: bool) {
+        self.ldac = high;
+    }
+
+    /// Number of SYNC frames received
+    pub fn frames(&self) -> usize {
+        self.frames
+    }
+
+    /// Contents of the DAC register of the channel at `addr`
+    pub fn dac_register(&self, addr: u8) -> Option<u16> {
+        IC::ADDRESSES
+            .contains(&addr)
+            .then(|| self.dac[addr as usize])
+    }
+
+    /// Code driving the output of the channel at `addr`, `None` if the
+    /// channel does not exist or is powered down
+    pub fn output(&self, addr: u8) -> Option<u16> {
+        (IC::ADDRESSES.contains(&addr) && self.power & (1 << addr) != 0)
+            .then(|| self.output[addr as usize])
+    }
+
+    /// Output range of the channel at `addr`
+    pub fn range(&self, addr: u8) -> Option<OutputRange> {
+        IC::ADDRESSES
+            .contains(&addr)
+            .then(|| OutputRange::from(self.range[addr as usize]))
+    }
+
+    /// Contents of the power control register
+    pub fn power(&self) -> u16 {
+        self.power
+    }
+
+    /// Contents of the configuration register
+    pub fn config(&self) -> Config {
+        self.config
+    }
+
+    /// Execute a latched frame
+    fn latch(&mut self, frame: [u8; 3]) {
+        self.frames += 1;
+        let cmd = CommandByte::from(frame[0]);
+        let data = ((frame[1] as u16) << 8) | frame[2] as u16;
+        let addr = cmd.addr();
+        let selected = IC::ADDRESSES
+            .iter()
+            .map(|&a| a as usize)
+            .filter(move |&a| addr == 4 || a == addr as usize);
+        if cmd.rw() {
+            let value = self.register(cmd.reg(), addr);
+            self.shift = if self.config.sdo_disable() {
+                [0; 3]
+            } else {
+                [frame[0], (value >> 8) as u8, value as u8]
+            };
+            return;
+        }
+        self.shift = [0; 3];
+        match cmd.reg() {
+            0b000 => {
+                for a in selected {
+                    self.dac[a] = data;
+                    if !self.ldac {
+                        self.output[a] = data;
+                    }
+                }
+            }
+            0b001 => {
+                for a in selected {
+                    self.range[a] = data & 0b111;
+                }
+            }
+            0b010 => {
+                let status = self.power & !IC::PU_MASK;
+                self.power = status | (data & IC::PU_MASK);
+            }
+            0b011 if addr == Function::Config as u8 => self.config = Config::from(data as u8 & 0xF),
+            0b011 if addr == Function::Clear as u8 => {
+                for &a in IC::ADDRESSES {
+                    let a = a as usize;
+                    let bipolar = OutputRange::from(self.range[a]).is_bipolar();
+                    self.dac[a] = match (bipolar, self.config.clr_select()) {
+                        (false, false) | (true, true) => 0x0000,
+                        (false, true) | (true, false) => 0x8000,
+                    };
+                    self.output[a] = self.dac[a];
+                }
+            }
+            0b011 if addr == Function::Load as u8 => self.output = self.dac,
+            _ => {}
+        }
+    }
+
+    fn register(&self, reg: u8, addr: u8) -> u16 {
+        let valid = IC::ADDRESSES.contains(&addr);
+        match reg {
+            0b000 if valid => self.dac[addr as usize],
+            0b001 if valid => self.range[addr as usize],
+            0b010 => self.power,
+            0b011 if addr == Function::Config as u8 => u8::from(self.config) as u16,
+            _ => 0,
+        }
+    }
+}
+
+impl<IC> ErrorType for Model<IC> {
+    type Error = Infallible;
+}
+
+impl<IC: Sealed> SpiDevice for Model<IC> {
+    fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), Infallible> {
+        let mut frame = [0u8; 3];
+        let mut count = 0;
+        let out = self.shift;
+        let mut clock = |tx: u8| {
+            let rx = out.get(count).copied().unwrap_or(0);
+            frame = [frame[1], frame[2], tx];
+            count += 1;
+            rx
+        };
+        for op in operations {
+            match op {
+                Operation::Write(buf) => buf.iter().for_each(|&b| {
+                    clock(b);
+                }),
+                Operation::Read(buf) => buf.iter_mut().for_each(|b| *b = clock(0)),
+                Operation::Transfer(read, write) => {
+                    for i in 0..read.len().max(write.len()) {
+                        let rx = clock(write.get(i).copied().unwrap_or(0));
+                        if let Some(b) = read.get_mut(i) {
+                            *b = rx;
+                        }
+                    }
+                }
+                Operation::TransferInPlace(buf) => buf.iter_mut().for_each(|b| *b = clock(*b)),
+                Operation::DelayNs(_) => {}
+            }
+        }
+        if count >= 3 {
+            self.latch(frame);
+        }
+        Ok(())
+    }
+}
+
+impl<IC: Sealed> embedded_hal_async::spi::SpiDevice for Model<IC> {
+    async fn transaction(
+        &mut self,
+        operations: &mut [Operation<'_, u8>],
+    ) -> Result<(), Infallible> {
+        SpiDevice::transaction(self, operations)
+    }
+}
@@ -0,0 +1,325 @@
+//! Envelope and LFO generators rendered into DAC codes
+//!
+//! The generators are tick driven and allocation free. A [`Bank`] owns one
+//! generator per output channel and writes the rendered codes to the DAC
+//! registers on every tick. With batching enabled all channels are updated
+//! together by a single load operation, which requires ~LDAC to be held high.
+//!
+//! Envelopes produce codes from zero to full scale, LFOs swing around
+//! mid-scale. For bipolar ranges the codes are converted to the coding mode
+//! of the device.
+//!
+//! ```ignore
+//! let mut bank = Bank::new([
+//!     (ChannelQuad::DacA, Source::Envelope(Envelope::new(Adsr::new(10, 50, 0x8000, 200)))),
+//!     (ChannelQuad::DacB, Source::Lfo(Lfo::new(Shape::Sine, 1000))),
+//! ])
+//! .with_batching(true);
+//! bank.gate(0, true);
+//! loop {
+//!     bank.tick(&mut dac)?;
+//! }
+//! ```
+use embedded_hal::spi::SpiDevice;
+
+use crate::channels::ChannelSet;
+use crate::{frame, private::Sealed, Ad57xxShared, Error, Function};
+
+/// Envelope parameters, times are given in ticks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Adsr {
+    /// Time to rise from zero to full scale
+    pub attack: u32,
+    /// Time to fall from full scale to the sustain level
+    pub decay: u32,
+    /// Level held while the gate is open
+    pub sustain: u16,
+    /// Time to fall from full scale to zero after the gate closes
+    pub release: u32,
+}
+
+impl Adsr {
+    /// Create a new set of envelope parameters
+    pub fn new(attack: u32, decay: u32, sustain: u16, release: u32) -> Self {
+        Adsr {
+            attack,
+            decay,
+            sustain,
+            release,
+        }
+    }
+}
+
+/// Stage of an envelope
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Stage {
+    /// Gate closed and release finished
+    Idle,
+    /// Rising towards full scale
+    Attack,
+    /// Falling towards the sustain level
+    Decay,
+    /// Holding the sustain level
+    Sustain,
+    /// Falling towards zero
+    Release,
+}
+
+// Levels are kept with 16 fractional bits
+const FULL_SCALE: u32 = 0xFFFF << 16;
+
+/// Linear ADSR envelope triggered by gate events
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Envelope {
+    adsr: Adsr,
+    stage: Stage,
+    level: u32,
+}
+
+impl Envelope {
+    /// Create an idle envelope
+    pub fn new(adsr: Adsr) -> Self {
+        Envelope {
+            adsr,
+            stage: Stage::Idle,
+            level: 0,
+        }
+    }
+
+    /// Change the envelope parameters, takes effect on the next tick
+    pub fn set_adsr(&mut self, adsr: Adsr) {
+        self.adsr = adsr;
+    }
+
+    /// Open the gate to (re)start the attack, close it to start the release
+    pub fn gate(&mut self, open: bool) {
+        self.stage = match (open, self.stage) {
+            (true, _) => Stage::Attack,
+            (false, Stage::Idle) => Stage::Idle,
+            (false, _) => Stage::Release,
+        };
+    }
+
+    /// Current stage of the envelope
+    pub fn stage(&self) -> Stage {
+        self.stage
+    }
+
+    /// Advance the envelope by one tick and return the new level
+    pub fn tick(&mut self) -> u16 {
+        let sustain = (self.adsr.sustain as u32) << 16;
+        match self.stage {
+            Stage::Idle => self.level = 0,
+            Stage::Attack => {
+                self.level = self.level.saturating_add(step(self.adsr.attack));
+                if self.level >= FULL_SCALE {
+                    self.level = FULL_SCALE;
+                    self.stage = Stage::Decay;
+                }
+            }
+            Stage::Decay => {
+                let step = (FULL_SCALE - sustain) / self.adsr.decay.max(1);
+                self.level = self.level.saturating_sub(step);
+                if self.level <= sustain {
+                    self.level = sustain;
+                    self.stage = Stage::Sustain;
+                }
+            }
+            Stage::Sustain => self.level = sustain,
+            Stage::Release => {
+                self.level = self.level.saturating_sub(step(self.adsr.release));
+                if self.level == 0 {
+                    self.stage = Stage::Idle;
+                }
+            }
+        }
+        (self.level >> 16) as u16
+    }
+}
+
+/// Level change per tick for a full scale segment of `ticks` length
+fn step(ticks: u32) -> u32 {
+    FULL_SCALE / ticks.max(1)
+}
+
+/// LFO waveform
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Shape {
+    /// Parabolic approximation of a sine
+    Sine,
+    /// Triangle
+    Triangle,
+    /// Rising sawtooth
+    SawUp,
+    /// Falling sawtooth
+    SawDown,
+    /// Square with 50% duty cycle
+    Square,
+}
+
+/// Low frequency oscillator
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Lfo {
+    shape: Shape,
+    increment: u32,
+    phase: u32,
+    depth: u16,
+}
+
+impl Lfo {
+    /// Create a full depth LFO with a period in ticks
+    pub fn new(shape: Shape, period: u32) -> Self {
+        Lfo {
+            shape,
+            increment: (u32::MAX / period.max(1)).wrapping_add(1),
+            phase: 0,
+            depth: 0xFFFF,
+        }
+    }
+
+    /// Scale the swing around mid-scale, 0xFFFF is full scale
+    pub fn with_depth(mut self, depth: u16) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    /// Change the waveform
+    pub fn set_shape(&mut self, shape: Shape) {
+        self.shape = shape;
+    }
+
+    /// Restart the waveform at the beginning of its period
+    pub fn sync(&mut self) {
+        self.phase = 0;
+    }
+
+    /// Return the level at the current phase and advance by one tick
+    pub fn tick(&mut self) -> u16 {
+        let phase = (self.phase >> 16) as i32;
+        self.phase = self.phase.wrapping_add(self.increment);
+        let val: i32 = match self.shape {
+            Shape::Sine => {
+                let half = phase & 0x7FFF;
+                let y = ((half * (0x8000 - half)) >> 13).min(0x7FFF);
+                if phase < 0x8000 {
+                    y
+                } else {
+                    -y
+                }
+            }
+            Shape::Triangle => {
+                let t = if phase < 0x8000 { phase } else { 0xFFFF - phase };
+                2 * t - 0x7FFF
+            }
+            Shape::SawUp => phase - 0x8000,
+            Shape::SawDown => 0x7FFF - phase,
+            Shape::Square => {
+                if phase < 0x8000 {
+                    0x7FFF
+                } else {
+                    -0x8000
+                }
+            }
+        };
+        let val = (val * (self.depth as i32 + 1)) >> 16;
+        (val + 0x8000) as u16
+    }
+}
+
+/// A generator driving one output
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Source {
+    /// ADSR envelope
+    Envelope(Envelope),
+    /// Low frequency oscillator
+    Lfo(Lfo),
+}
+
+impl Source {
+    /// Advance the generator by one tick and return the offset binary code
+    pub fn tick(&mut self) -> u16 {
+        match self {
+            Source::Envelope(env) => env.tick(),
+            Source::Lfo(lfo) => lfo.tick(),
+        }
+    }
+}
+
+/// A set of generators, each driving its own DAC channel
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Bank<CH, const N: usize> {
+    outputs: [(CH, Source); N],
+    batched: bool,
+}
+
+impl<CH: Copy + Into<u8>, const N: usize> Bank<CH, N> {
+    /// Create a bank from channel and generator pairs
+    pub fn new(outputs: [(CH, Source); N]) -> Self {
+        Bank {
+            outputs,
+            batched: false,
+        }
+    }
+
+    /// Update all outputs together through a single load operation
+    pub fn with_batching(mut self, batched: bool) -> Self {
+        self.batched = batched;
+        self
+    }
+
+    /// Access the generator of an output
+    pub fn source_mut(&mut self, index: usize) -> Option<&mut Source> {
+        self.outputs.get_mut(index).map(|(_, source)| source)
+    }
+
+    /// Send a gate event to the envelope of an output
+    pub fn gate(&mut self, index: usize, open: bool) {
+        if let Some(Source::Envelope(env)) = self.source_mut(index) {
+            env.gate(open);
+        }
+    }
+
+    /// Restart all LFOs of the bank
+    pub fn sync(&mut self) {
+        for (_, source) in self.outputs.iter_mut() {
+            if let Source::Lfo(lfo) = source {
+                lfo.sync();
+            }
+        }
+    }
+
+    /// Advance all generators by one tick and write the changed outputs.
+    ///
+    /// Returns [`Error::InvalidArgument`] if an output is not assigned to an
+    /// individual channel, before any generator is advanced.
+    pub fn tick<DEV, IC, E>(&mut self, dac: &mut Ad57xxShared<DEV, IC>) -> Result<(), Error<E>>
+    where
+        DEV: SpiDevice<Error = E>,
+        IC: Sealed<CH = CH>,
+    {
+        let individual = |&(chan, _): &(CH, Source)| IC::ADDRESSES.contains(&chan.into());
+        if !self.outputs.iter().all(individual) {
+            return Err(Error::InvalidArgument);
+        }
+        let mut changed = false;
+        for (chan, source) in self.outputs.iter_mut() {
+            let addr = (*chan).into() as usize;
+            let code = dac.coding.offset_binary(dac.ranges[addr], source.tick());
+            if dac.codes[addr] != code {
+                dac.set_dac_output(ChannelSet::from_channels(&[*chan]), code)?;
+                changed = true;
+            }
+        }
+        if self.batched && changed {
+            dac.write_frame(&frame(0b011, Function::Load as u8, 0x0000))?;
+        }
+        Ok(())
+    }
+}
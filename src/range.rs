@@ -0,0 +1,120 @@
+//! Glitch-safe output range switching
+//!
+//! Writing the range select register of a live channel changes the gain
+//! immediately, with the old code still loaded. [`Ad57xxShared::change_range_safely`]
+//! recomputes the code so the output voltage is preserved as closely as the
+//! new range allows and orders the writes to keep the intermediate step small.
+//!
+//! ```ignore
+//! // Keeps a 2.5V output at 2.5V while switching to the bipolar range
+//! dac.change_range_safely(ChannelQuad::DacA, OutputRange::Bipolar10V, RangeRoute::Direct)?;
+//! ```
+use embedded_hal::spi::SpiDevice;
+
+use crate::{frame, private::Sealed, Ad57xxShared, Error, Function, OutputRange};
+
+/// How the output is routed while the range is changed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum RangeRoute {
+    /// Change range and code directly, in the order with the smallest
+    /// intermediate excursion
+    Direct,
+    /// Power down the channel during the change. The output is pulled to
+    /// ground through the internal resistor while powered down.
+    PowerDown,
+    /// Issue a clear before the change. The clear affects all channels of
+    /// the device, the codes of the other channels are written back and
+    /// loaded together with the new code.
+    Clear,
+}
+
+impl<DEV, IC, E> Ad57xxShared<DEV, IC>
+where
+    DEV: SpiDevice<Error = E>,
+    IC: Sealed,
+{
+    /// Switch the output range of a channel while preserving its output voltage.
+    ///
+    /// The new code is calculated from the last written code and range and
+    /// clamped to the new range. The new code is loaded through the load
+    /// function of the control register, which also loads any pending values
+    /// of the other channels.
+    pub fn change_range_safely(
+        &mut self,
+        chan: IC::CH,
+        range: OutputRange,
+        route: RangeRoute,
+    ) -> Result<(), Error<E>> {
+        if range == OutputRange::InvalidReadback {
+            return Err(Error::InvalidArgument);
+        }
+        let addr: u8 = chan.into();
+        for &a in IC::ADDRESSES.iter().filter(|&&a| addr == 4 || a == addr) {
+            self.change_channel_range(a, range, route)?;
+        }
+        Ok(())
+    }
+
+    fn change_channel_range(
+        &mut self,
+        addr: u8,
+        range: OutputRange,
+        route: RangeRoute,
+    ) -> Result<(), Error<E>> {
+        let old_range = self.ranges[addr as usize];
+        // The voltage math works on offset binary codes
+        let old_code = self.coding.offset_binary(old_range, self.codes[addr as usize]);
+        let level = old_range.level(old_code);
+        let new_code = range.code(level).ok_or(Error::InvalidArgument)?;
+        let code = self.coding.offset_binary(range, new_code);
+        // Offset binary codes of the intermediate steps: the old register value
+        // with the new range and the new register value with the old range
+        let range_step = self.coding.offset_binary(range, self.codes[addr as usize]);
+        let code_step = self.coding.offset_binary(old_range, code);
+        let load = frame(0b011, Function::Load as u8, 0x0000);
+        let pu = 1u16 << addr;
+        let powered = self.pcfg & pu != 0;
+
+        match route {
+            RangeRoute::Direct => {
+                // Either the new range is applied to the old code first or the
+                // new code is loaded with the old range, pick the intermediate
+                // output closest to the voltage being preserved.
+                let range_first = (range.level(range_step) - level).abs();
+                let code_first = (old_range.level(code_step) - level).abs();
+                if range_first <= code_first {
+                    self.write_frame(&frame(0b001, addr, range as u16))?;
+                    self.write_frame(&frame(0b000, addr, code))?;
+                    self.write_frame(&load)?;
+                } else {
+                    self.write_frame(&frame(0b000, addr, code))?;
+                    self.write_frame(&load)?;
+                    self.write_frame(&frame(0b001, addr, range as u16))?;
+                }
+            }
+            RangeRoute::PowerDown => {
+                if powered {
+                    self.write_frame(&frame(0b010, 0, self.pcfg & IC::PU_MASK & !pu))?;
+                }
+                self.write_frame(&frame(0b001, addr, range as u16))?;
+                self.write_frame(&frame(0b000, addr, code))?;
+                self.write_frame(&load)?;
+                if powered {
+                    self.write_frame(&frame(0b010, 0, (self.pcfg & IC::PU_MASK) | pu))?;
+                }
+            }
+            RangeRoute::Clear => {
+                let saved = self.codes;
+                self.write_frame(&frame(0b011, Function::Clear as u8, 0x0000))?;
+                self.write_frame(&frame(0b001, addr, range as u16))?;
+                self.write_frame(&frame(0b000, addr, code))?;
+                for &a in IC::ADDRESSES.iter().filter(|&&a| a != addr) {
+                    self.write_frame(&frame(0b000, a, saved[a as usize]))?;
+                }
+                self.write_frame(&load)?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,104 @@
+//! Recovery from a power-on reset
+//!
+//! A glitch on the supply resets the device: all channels power down, the
+//! ranges and DAC registers return to zero and the control register to its
+//! defaults, while the driver state still holds the last written values.
+//! [`Ad57xxShared::recover`] detects the reset by reading back the power
+//! control, control, range select and DAC registers,
+//! [`Ad57xxShared::recover_with`]
+//! asks a hook instead, e.g. a brown-out flag or a supply monitor. Both write
+//! the driver state back to the device and report whether they did.
+//!
+//! ```ignore
+//! if dac.recover()?.is_recovered() {
+//!     defmt::warn!("DAC reset detected, configuration restored");
+//! }
+//! ```
+use embedded_hal::spi::SpiDevice;
+
+use crate::{private::Sealed, Ad57xxShared, Error};
+
+/// Outcome of a recovery attempt
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Recovery {
+    /// No reset was detected, nothing was written
+    Healthy,
+    /// A reset was detected and the driver state was written to the device
+    Recovered,
+}
+
+impl Recovery {
+    /// Returns true if the device was re-initialised
+    pub fn is_recovered(&self) -> bool {
+        matches!(self, Recovery::Recovered)
+    }
+}
+
+impl<DEV, IC, E> Ad57xxShared<DEV, IC>
+where
+    DEV: SpiDevice<Error = E>,
+    IC: Sealed,
+{
+    /// Write the driver state to the device with [`Ad57xxShared::restore`],
+    /// powering the channels up only after their ranges and codes are
+    /// loaded.
+    pub fn reinitialize(&mut self) -> Result<(), Error<E>> {
+        self.restore(&self.cached_state())
+    }
+
+    /// Re-initialise the device if `reset` returns true
+    pub fn recover_with(&mut self, reset: impl FnOnce() -> bool) -> Result<Recovery, Error<E>> {
+        if !reset() {
+            return Ok(Recovery::Healthy);
+        }
+        self.reinitialize()?;
+        Ok(Recovery::Recovered)
+    }
+
+    /// Returns true if the power control, control, range select or DAC
+    /// registers differ from the driver state. Only the 12 DAC register MSBs
+    /// implemented by every part are compared.
+    ///
+    /// Returns [`Error::InvalidArgument`] if SDO has been disabled, use
+    /// [`Ad57xxShared::recover_with`] then.
+    #[cfg(feature = "readback")]
+    pub fn detect_reset(&mut self) -> Result<bool, Error<E>> {
+        use crate::selftest::DAC_MASK;
+        use crate::{read_cmd, Function};
+
+        if self.cfg.sdo_disable() {
+            return Err(Error::InvalidArgument);
+        }
+        let power = self.read_frame(read_cmd(0b010, 0))?;
+        if (power ^ self.pcfg) & IC::PU_MASK != 0 {
+            return Ok(true);
+        }
+        let config = self.read_frame(read_cmd(0b011, Function::Config as u8))?;
+        if config & 0b1111 != u8::from(self.cfg) as u16 & 0b1111 {
+            return Ok(true);
+        }
+        for &addr in IC::ADDRESSES {
+            let range = self.read_frame(read_cmd(0b001, addr))?;
+            if range & 0b111 != self.ranges[addr as usize] as u16 {
+                return Ok(true);
+            }
+        }
+        // A reset with everything else at its defaults only shows in the
+        // DAC registers
+        for &addr in IC::ADDRESSES {
+            let code = self.read_frame(read_cmd(0b000, addr))?;
+            if (code ^ self.codes[addr as usize]) & DAC_MASK != 0 {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Detect a reset with readback and re-initialise the device after one
+    #[cfg(feature = "readback")]
+    pub fn recover(&mut self) -> Result<Recovery, Error<E>> {
+        let reset = self.detect_reset()?;
+        self.recover_with(|| reset)
+    }
+}
@@ -0,0 +1,193 @@
+//! Typed register access
+//!
+//! Every register is a type with an associated value type, so a write can
+//! not pair a register with the wrong kind of data. [`Ad57xx::write`] and
+//! [`Ad57xx::read`] are a dynamic layer over this API.
+//!
+//! ```ignore
+//! dac.write_register(DacReg(ChannelQuad::DacA), 0x8000)?;
+//! dac.write_register(RangeReg(ChannelQuad::AllDacs), OutputRange::Bipolar10V)?;
+//! dac.write_register(Control::<Config>::new(), Config::default())?;
+//! dac.write_register(Control::<Load>::new(), ())?;
+//! let range = dac.read_register(RangeReg(ChannelQuad::DacA))?;
+//! ```
+//!
+//! [`Ad57xx::write`]: crate::Ad57xx::write
+//! [`Ad57xx::read`]: crate::Ad57xx::read
+use core::marker::PhantomData;
+
+use crate::{Config, Function, OutputRange};
+
+/// A register of a part with channel type `CH` and power configuration type
+/// `PCFG`
+pub trait Register<CH, PCFG>: Copy {
+    /// Value written to the register
+    type Value;
+
+    /// Register and address bits of the command byte
+    fn address(&self) -> (u8, u8);
+
+    /// 16 bit data of a value, `None` if the value can not be written
+    fn encode(value: Self::Value) -> Option<u16>;
+}
+
+/// A register that can be read back
+pub trait Readable<CH, PCFG>: Register<CH, PCFG> {
+    /// Value of the 16 bit data read back
+    fn decode(data: u16) -> Self::Value;
+}
+
+/// DAC register of the channel(s)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct DacReg<CH>(pub CH);
+
+impl<CH: Copy + Into<u8>, PCFG> Register<CH, PCFG> for DacReg<CH> {
+    type Value = u16;
+
+    fn address(&self) -> (u8, u8) {
+        (0b000, self.0.into())
+    }
+
+    fn encode(value: u16) -> Option<u16> {
+        Some(value)
+    }
+}
+
+impl<CH: Copy + Into<u8>, PCFG> Readable<CH, PCFG> for DacReg<CH> {
+    fn decode(data: u16) -> u16 {
+        data
+    }
+}
+
+/// Range select register of the channel(s)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct RangeReg<CH>(pub CH);
+
+impl<CH: Copy + Into<u8>, PCFG> Register<CH, PCFG> for RangeReg<CH> {
+    type Value = OutputRange;
+
+    fn address(&self) -> (u8, u8) {
+        (0b001, self.0.into())
+    }
+
+    fn encode(value: OutputRange) -> Option<u16> {
+        // Only a decoding result, there is no such range code
+        (value != OutputRange::InvalidReadback).then_some(value as u16)
+    }
+}
+
+impl<CH: Copy + Into<u8>, PCFG> Readable<CH, PCFG> for RangeReg<CH> {
+    fn decode(data: u16) -> OutputRange {
+        OutputRange::from(data)
+    }
+}
+
+/// Power control register
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PowerReg;
+
+impl<CH, PCFG> Register<CH, PCFG> for PowerReg
+where
+    u16: From<PCFG> + Into<PCFG>,
+{
+    type Value = PCFG;
+
+    fn address(&self) -> (u8, u8) {
+        (0b010, 0)
+    }
+
+    fn encode(value: PCFG) -> Option<u16> {
+        Some(u16::from(value))
+    }
+}
+
+impl<CH, PCFG> Readable<CH, PCFG> for PowerReg
+where
+    u16: From<PCFG> + Into<PCFG>,
+{
+    fn decode(data: u16) -> PCFG {
+        data.into()
+    }
+}
+
+/// Function of the control register, selected by `F`
+#[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Control<F>(PhantomData<F>);
+
+impl<F> Control<F> {
+    /// The control register function
+    pub const fn new() -> Self {
+        Control(PhantomData)
+    }
+}
+
+impl<F> Default for Control<F> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<F> Clone for Control<F> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<F> Copy for Control<F> {}
+
+/// No operation, used for readback
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Nop;
+
+/// Set the DAC registers to the clear code
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Clear;
+
+/// Load the DAC registers to the outputs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Load;
+
+impl<CH, PCFG> Register<CH, PCFG> for Control<Config> {
+    type Value = Config;
+
+    fn address(&self) -> (u8, u8) {
+        (0b011, Function::Config as u8)
+    }
+
+    fn encode(value: Config) -> Option<u16> {
+        Some(u8::from(value) as u16)
+    }
+}
+
+impl<CH, PCFG> Readable<CH, PCFG> for Control<Config> {
+    fn decode(data: u16) -> Config {
+        Config::from(data as u8)
+    }
+}
+
+macro_rules! function {
+    ($($ty:ident => $function:ident),*) => {
+        $(
+            impl<CH, PCFG> Register<CH, PCFG> for Control<$ty> {
+                type Value = ();
+
+                fn address(&self) -> (u8, u8) {
+                    (0b011, Function::$function as u8)
+                }
+
+                fn encode(_: ()) -> Option<u16> {
+                    Some(0)
+                }
+            }
+        )*
+    };
+}
+
+function!(Nop => Nop, Clear => Clear, Load => Load);
@@ -0,0 +1,142 @@
+//! Periodic integrity scrubbing of the device registers
+//!
+//! Supply glitches or radiated noise can silently reset the registers of the
+//! DAC. The scrubber reads back one register at a time, round-robin, and
+//! compares it against the values last written by the driver. Drift is either
+//! reported or repaired by rewriting the register.
+//!
+//! ```ignore
+//! // Check at most two registers per call from a low priority task
+//! let report = dac.scrub(2, ScrubMode::Repair)?;
+//! if let Some(drift) = report.first {
+//!     defmt::warn!("register drifted: {:?}", drift.register);
+//! }
+//! ```
+use embedded_hal::spi::SpiDevice;
+
+use crate::{frame, private::Sealed, read_cmd, Ad57xxShared, Command, Error, Function};
+
+/// What to do when a register does not match the driver state
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ScrubMode {
+    /// Only report the drift
+    Report,
+    /// Report the drift and rewrite the register from the driver state
+    Repair,
+}
+
+/// A register whose contents differ from the driver state
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Drift<CH> {
+    /// The register that was checked
+    pub register: Command<CH>,
+    /// The value expected from the driver state
+    pub expected: u16,
+    /// The value read back from the device
+    pub actual: u16,
+}
+
+/// Result of a single scrubber call
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ScrubReport<CH> {
+    /// Number of registers read back
+    pub checked: usize,
+    /// Number of registers that did not match the driver state
+    pub drifted: usize,
+    /// The first drifted register found during this call
+    pub first: Option<Drift<CH>>,
+}
+
+impl<DEV, IC, E> Ad57xxShared<DEV, IC>
+where
+    DEV: SpiDevice<Error = E>,
+    IC: Sealed,
+{
+    /// Number of registers covered by one full scrub cycle
+    pub fn scrub_len(&self) -> usize {
+        2 * IC::CHANNELS.len() + 2
+    }
+
+    /// Check up to `budget` registers against the driver state, continuing
+    /// where the previous call left off.
+    ///
+    /// The registers are visited in the order DAC registers, range select
+    /// registers, power control register and control register. Only the
+    /// power-up bits of the power control register are compared, the
+    /// thermal shutdown and overcurrent flags are status bits.
+    ///
+    /// > Note that a repaired DAC register still has to be loaded to update
+    /// > the output unless ~LDAC is tied low.
+    pub fn scrub(
+        &mut self,
+        budget: usize,
+        mode: ScrubMode,
+    ) -> Result<ScrubReport<IC::CH>, Error<E>> {
+        let mut report = ScrubReport {
+            checked: 0,
+            drifted: 0,
+            first: None,
+        };
+        while report.checked < budget {
+            let pos = self.scrub_pos % self.scrub_len();
+            if let Some(drift) = self.scrub_register(pos, mode)? {
+                report.drifted += 1;
+                report.first.get_or_insert(drift);
+            }
+            self.scrub_pos = (pos + 1) % self.scrub_len();
+            report.checked += 1;
+        }
+        Ok(report)
+    }
+
+    /// Check a single register in the scrub sequence
+    fn scrub_register(
+        &mut self,
+        pos: usize,
+        mode: ScrubMode,
+    ) -> Result<Option<Drift<IC::CH>>, Error<E>> {
+        let n = IC::CHANNELS.len();
+        let (register, reg, addr, expected, mask) = if pos < n {
+            let addr = IC::ADDRESSES[pos];
+            let code = self.codes[addr as usize];
+            (Command::DacRegister(IC::CHANNELS[pos]), 0b000, addr, code, 0xFFFF)
+        } else if pos < 2 * n {
+            let addr = IC::ADDRESSES[pos - n];
+            let range = self.ranges[addr as usize] as u16;
+            (
+                Command::RangeSelectRegister(IC::CHANNELS[pos - n]),
+                0b001,
+                addr,
+                range,
+                0b111,
+            )
+        } else if pos == 2 * n {
+            (Command::PowerControlRegister, 0b010, 0, self.pcfg, IC::PU_MASK)
+        } else {
+            let cfg = u8::from(self.cfg) as u16;
+            (
+                Command::ControlRegister(Function::Config),
+                0b011,
+                Function::Config as u8,
+                cfg,
+                0b1111,
+            )
+        };
+
+        let actual = self.read_frame(read_cmd(reg, addr))?;
+        if actual & mask == expected & mask {
+            return Ok(None);
+        }
+        if mode == ScrubMode::Repair {
+            self.write_frame(&frame(reg, addr, expected))?;
+        }
+        Ok(Some(Drift {
+            register,
+            expected,
+            actual,
+        }))
+    }
+}
@@ -0,0 +1,174 @@
+//! Connectivity self-test using readback
+//!
+//! [`Ad57xxShared::self_test`] writes distinctive patterns to the control
+//! register and to the DAC and range select registers of every channel,
+//! reads them back and restores the original values. The result tells a
+//! missing device, stuck data lines and a disabled SDO output apart.
+//!
+//! > The DAC registers are overwritten during the test. Run it while the
+//! > outputs are powered down or disconnected, e.g. in production test.
+//!
+//! ```ignore
+//! match dac.self_test()? {
+//!     SelfTest::Pass => {}
+//!     fault => defmt::error!("DAC self-test failed: {:?}", fault),
+//! }
+//! ```
+use embedded_hal::spi::SpiDevice;
+
+use crate::{frame, private::Sealed, read_cmd, Ad57xxShared, Command, Error, Function};
+
+/// Outcome of a self-test
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SelfTest<CH> {
+    /// Every pattern was read back correctly
+    Pass,
+    /// Every readback returned the same idle level, 0x0000 or 0xFFFF. The
+    /// device is not connected, not powered or SDO is not wired.
+    NoResponse {
+        /// The level read back
+        level: u16,
+    },
+    /// Bits of a register did not follow the written patterns
+    StuckBits {
+        /// The first register with stuck bits
+        register: Command<CH>,
+        /// Bits read back as 1 when written as 0
+        stuck_high: u16,
+        /// Bits read back as 0 when written as 1
+        stuck_low: u16,
+    },
+    /// The device works, but SDO was disabled in the control register before
+    /// the test. The configuration has been restored from the driver state.
+    SdoDisabled,
+}
+
+impl<CH> SelfTest<CH> {
+    /// Returns true if the device passed the test
+    pub fn is_pass(&self) -> bool {
+        matches!(self, SelfTest::Pass)
+    }
+}
+
+// Control register patterns, bit 0 clear to keep SDO enabled
+const CONFIG_PATTERNS: [u16; 2] = [0b1010, 0b0100];
+// The 12 and 14 bit parts read back don't-care low bits, only the 12 MSBs
+// implemented by the whole family are checked
+const DAC_PATTERNS: [u16; 2] = [0xA5A0, 0x5A50];
+pub(crate) const DAC_MASK: u16 = 0xFFF0;
+const RANGE_PATTERNS: [u16; 2] = [0b101, 0b010];
+
+/// Readback statistics of a test run
+struct Tally<CH> {
+    first: Option<u16>,
+    uniform: bool,
+    stuck: Option<(Command<CH>, u16, u16)>,
+}
+
+impl<DEV, IC, E> Ad57xxShared<DEV, IC>
+where
+    DEV: SpiDevice<Error = E>,
+    IC: Sealed,
+{
+    /// Check SPI connectivity and SDO wiring with readback patterns.
+    ///
+    /// Returns an error only if a SPI transaction fails. Every register is
+    /// read before the test and restored afterwards. A register whose
+    /// readback can not be trusted, because its patterns did not read back
+    /// or because SDO was disabled, is restored from the driver state
+    /// instead. The driver state is updated with the restored values.
+    pub fn self_test(&mut self) -> Result<SelfTest<IC::CH>, Error<E>> {
+        let mut tally = Tally {
+            first: None,
+            uniform: true,
+            stuck: None,
+        };
+
+        // The control register goes first as it enables SDO. It reads back
+        // as zero while SDO is disabled.
+        let register = Command::ControlRegister(Function::Config);
+        let cached = u8::from(self.cfg) as u16;
+        let (initial, config) =
+            self.check(&mut tally, register, cached, &CONFIG_PATTERNS, 0b1111)?;
+        for &chan in IC::CHANNELS {
+            let addr = chan.into() as usize;
+            let cached = self.codes[addr];
+            self.check(
+                &mut tally,
+                Command::DacRegister(chan),
+                cached,
+                &DAC_PATTERNS,
+                DAC_MASK,
+            )?;
+            let register = Command::RangeSelectRegister(chan);
+            let cached = self.ranges[addr] as u16;
+            self.check(&mut tally, register, cached, &RANGE_PATTERNS, 0b111)?;
+        }
+        let expected = u8::from(self.cfg.with_sdo_disable(false)) as u16;
+        let sdo_disabled = self.cfg.sdo_disable() || (initial == 0 && expected != 0);
+        self.write_frame(&frame(0b011, Function::Config as u8, config))?;
+
+        Ok(match (tally.uniform, tally.first, tally.stuck) {
+            (true, Some(level @ (0x0000 | 0xFFFF)), _) => SelfTest::NoResponse { level },
+            (_, _, Some((register, stuck_high, stuck_low))) => SelfTest::StuckBits {
+                register,
+                stuck_high,
+                stuck_low,
+            },
+            _ if sdo_disabled => SelfTest::SdoDisabled,
+            _ => SelfTest::Pass,
+        })
+    }
+
+    /// Write and read back the patterns of a single register, then restore
+    /// it. Returns the value read before the test and the value to restore.
+    /// The control register is left for the caller to restore last, as it
+    /// may disable SDO.
+    fn check(
+        &mut self,
+        tally: &mut Tally<IC::CH>,
+        register: Command<IC::CH>,
+        cached: u16,
+        patterns: &[u16],
+        mask: u16,
+    ) -> Result<(u16, u16), Error<E>> {
+        let reg = u8::from(register);
+        let addr = match register {
+            Command::DacRegister(chan) | Command::RangeSelectRegister(chan) => chan.into(),
+            Command::PowerControlRegister => 0,
+            Command::ControlRegister(function) => function as u8,
+        };
+        let original = self.read_frame(read_cmd(reg, addr))?;
+        let (mut stuck_high, mut stuck_low) = (0, 0);
+        for &pattern in patterns {
+            self.spi
+                .write(&frame(reg, addr, pattern))
+                .map_err(Error::Spi)?;
+            let actual = self.read_frame(read_cmd(reg, addr))?;
+            if *tally.first.get_or_insert(actual) != actual {
+                tally.uniform = false;
+            }
+            stuck_high |= actual & !pattern & mask;
+            stuck_low |= !actual & pattern & mask;
+        }
+        if (stuck_high | stuck_low) != 0 && tally.stuck.is_none() {
+            tally.stuck = Some((register, stuck_high, stuck_low));
+        }
+
+        let trusted = match register {
+            _ if (stuck_high | stuck_low) != 0 => false,
+            // Zero is also what a disabled SDO output reads
+            Command::ControlRegister(_) => original != 0 && !self.cfg.sdo_disable(),
+            Command::RangeSelectRegister(_) => original & !mask == 0 && original & mask <= 0b101,
+            // Any code is valid, including the bits outside the mask
+            Command::DacRegister(_) => true,
+            _ => original & !mask == 0,
+        };
+        let restore = if trusted { original } else { cached };
+        if !matches!(register, Command::ControlRegister(_)) {
+            self.write_frame(&frame(reg, addr, restore))?;
+        }
+        Ok((original, restore))
+    }
+}
@@ -0,0 +1,574 @@
+//! Stimulus sequences described in a line based text format
+//!
+//! Every line holds one command, tokens are separated by whitespace and `#`
+//! starts a comment. A [`Sequence`] is validated completely when it is
+//! parsed, an [`Executor`] then plays it back on any [`Ad57xx`] device.
+//!
+//! ```text
+//! # Slow sweep on channel A, fixed level on channel B
+//! range all bi10
+//! power all on
+//! set b 0x8000
+//! set a -2.5V
+//! wait 10ms
+//! ramp a -2.5V 2.5V 100ms 50
+//! load
+//! ```
+//!
+//! | Command                              | Effect                                 |
+//! |--------------------------------------|----------------------------------------|
+//! | `set CHAN VALUE`                     | Write the DAC register                 |
+//! | `range CHAN RANGE`                   | Select the output range                |
+//! | `power CHAN on\|off`                 | Power a channel up or down             |
+//! | `wait DURATION`                      | Pause the sequence                     |
+//! | `ramp CHAN FROM TO DURATION STEPS`   | Step linearly from one value to another|
+//! | `load`                               | Load the DAC registers                 |
+//! | `clear`                              | Set the DAC registers to the clear code|
+//!
+//! `CHAN` is `a` to `d` or `all`. A `VALUE` is a code, decimal or `0x`
+//! hexadecimal, or a voltage with a `V` or `mV` suffix. `RANGE` is one of
+//! `uni5`, `uni10`, `uni10.8`, `bi5`, `bi10` and `bi10.8`. A `DURATION` is an
+//! integer with a `us`, `ms` or `s` suffix.
+//!
+//! ```ignore
+//! let seq = Sequence::parse(include_bytes!("stimulus.seq"))?;
+//! Executor::new(delay).run(&mut dac, &seq)?;
+//! ```
+use embedded_hal::delay::DelayNs;
+
+use crate::ad57x2::ChannelDual;
+use crate::ad57x4::ChannelQuad;
+use crate::{voltage, Ad57xx, Command, Error, OutputRange};
+
+/// Position in the sequence text, both counted from 1
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Position {
+    /// Line number
+    pub line: usize,
+    /// Byte offset within the line
+    pub column: usize,
+}
+
+/// Reasons a sequence is rejected
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ParseErrorKind {
+    /// The command is not known
+    UnknownCommand,
+    /// The command needs more arguments
+    MissingArgument,
+    /// The command has too many arguments
+    UnexpectedArgument,
+    /// Not a channel name
+    InvalidChannel,
+    /// Not a code or voltage, or a ramp between a code and a voltage
+    InvalidValue,
+    /// Not a range name
+    InvalidRange,
+    /// Neither `on` nor `off`
+    InvalidPower,
+    /// Not a duration or too long
+    InvalidDuration,
+    /// The number of ramp steps is not between 1 and 65535
+    InvalidSteps,
+}
+
+/// Error of [`Sequence::parse`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ParseError {
+    /// Where the error was found
+    pub position: Position,
+    /// What is wrong
+    pub kind: ParseErrorKind,
+}
+
+/// Error of [`Executor::run`]
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ExecError<E> {
+    /// Position of the failed command
+    pub position: Position,
+    /// [`Error::InvalidArgument`] if the channel does not exist, its range is
+    /// unknown or the voltage is outside of it.
+    pub error: Error<E>,
+}
+
+/// Channel selected by a command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Channel {
+    /// Channel index, 0 is channel A
+    Index(u8),
+    /// All channels
+    All,
+}
+
+impl TryFrom<Channel> for ChannelQuad {
+    type Error = ();
+    fn try_from(chan: Channel) -> Result<Self, ()> {
+        match chan {
+            Channel::Index(0) => Ok(ChannelQuad::DacA),
+            Channel::Index(1) => Ok(ChannelQuad::DacB),
+            Channel::Index(2) => Ok(ChannelQuad::DacC),
+            Channel::Index(3) => Ok(ChannelQuad::DacD),
+            Channel::All => Ok(ChannelQuad::AllDacs),
+            _ => Err(()),
+        }
+    }
+}
+
+impl TryFrom<Channel> for ChannelDual {
+    type Error = ();
+    fn try_from(chan: Channel) -> Result<Self, ()> {
+        match chan {
+            Channel::Index(0) => Ok(ChannelDual::DacA),
+            Channel::Index(1) => Ok(ChannelDual::DacB),
+            Channel::All => Ok(ChannelDual::AllDacs),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Value written to a DAC register
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Value {
+    /// Offset binary code
+    Code(u16),
+    /// Output voltage in microvolts
+    Microvolts(i32),
+}
+
+/// A single command of a sequence
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Step {
+    /// Write the DAC register
+    Set {
+        /// Channel to write
+        channel: Channel,
+        /// Value to write
+        value: Value,
+    },
+    /// Select the output range
+    Range {
+        /// Channel to configure
+        channel: Channel,
+        /// New output range
+        range: OutputRange,
+    },
+    /// Power a channel up or down
+    Power {
+        /// Channel to configure
+        channel: Channel,
+        /// Power up if true
+        on: bool,
+    },
+    /// Pause the sequence
+    Wait {
+        /// Pause in microseconds
+        us: u32,
+    },
+    /// Write `steps + 1` values from `from` to `to`, evenly spread over the
+    /// duration
+    Ramp {
+        /// Channel to write
+        channel: Channel,
+        /// First value
+        from: Value,
+        /// Last value
+        to: Value,
+        /// Duration of the ramp in microseconds
+        us: u32,
+        /// Number of steps
+        steps: u16,
+    },
+    /// Load the DAC registers
+    Load,
+    /// Set the DAC registers to the clear code
+    Clear,
+}
+
+/// A validated sequence
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Sequence<'a> {
+    text: &'a [u8],
+}
+
+impl<'a> Sequence<'a> {
+    /// Parse and validate a sequence, reporting the first error
+    pub fn parse(text: &'a [u8]) -> Result<Self, ParseError> {
+        for (number, line) in lines(text) {
+            parse_line(number, line)?;
+        }
+        Ok(Sequence { text })
+    }
+
+    /// The commands of the sequence with their position
+    pub fn steps(&self) -> impl Iterator<Item = (Position, Step)> + 'a {
+        lines(self.text).filter_map(|(number, line)| parse_line(number, line).ok().flatten())
+    }
+}
+
+fn lines(text: &[u8]) -> impl Iterator<Item = (usize, &[u8])> {
+    text.split(|&b| b == b'\n').zip(1..).map(|(line, number)| {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        let end = line.iter().position(|&b| b == b'#').unwrap_or(line.len());
+        (number, &line[..end])
+    })
+}
+
+/// Whitespace separated tokens of a line
+struct Tokens<'a> {
+    line: &'a [u8],
+    number: usize,
+    pos: usize,
+}
+
+impl<'a> Tokens<'a> {
+    fn next(&mut self) -> Option<(Position, &'a [u8])> {
+        let rest = &self.line[self.pos..];
+        let start = self.pos + rest.iter().position(|b| !b.is_ascii_whitespace())?;
+        let len = self.line[start..]
+            .iter()
+            .position(|b| b.is_ascii_whitespace())
+            .unwrap_or(self.line.len() - start);
+        self.pos = start + len;
+        Some((self.at(start), &self.line[start..start + len]))
+    }
+
+    fn at(&self, offset: usize) -> Position {
+        Position {
+            line: self.number,
+            column: offset + 1,
+        }
+    }
+
+    /// Parse the next token, which must exist
+    fn arg<T>(
+        &mut self,
+        parse: fn(&str) -> Option<T>,
+        kind: ParseErrorKind,
+    ) -> Result<(Position, T), ParseError> {
+        let Some((position, token)) = self.next() else {
+            return Err(ParseError {
+                position: self.at(self.line.len()),
+                kind: ParseErrorKind::MissingArgument,
+            });
+        };
+        core::str::from_utf8(token)
+            .ok()
+            .and_then(parse)
+            .map(|value| (position, value))
+            .ok_or(ParseError { position, kind })
+    }
+
+    fn finish(&mut self) -> Result<(), ParseError> {
+        match self.next() {
+            Some((position, _)) => Err(ParseError {
+                position,
+                kind: ParseErrorKind::UnexpectedArgument,
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+fn parse_line(number: usize, line: &[u8]) -> Result<Option<(Position, Step)>, ParseError> {
+    use ParseErrorKind::*;
+
+    let mut tokens = Tokens {
+        line,
+        number,
+        pos: 0,
+    };
+    let Some((position, cmd)) = tokens.next() else {
+        return Ok(None);
+    };
+    let step = match cmd {
+        b"set" => Step::Set {
+            channel: tokens.arg(parse_channel, InvalidChannel)?.1,
+            value: tokens.arg(parse_value, InvalidValue)?.1,
+        },
+        b"range" => Step::Range {
+            channel: tokens.arg(parse_channel, InvalidChannel)?.1,
+            range: tokens.arg(parse_range, InvalidRange)?.1,
+        },
+        b"power" => Step::Power {
+            channel: tokens.arg(parse_channel, InvalidChannel)?.1,
+            on: tokens.arg(parse_power, InvalidPower)?.1,
+        },
+        b"wait" => Step::Wait {
+            us: tokens.arg(parse_duration, InvalidDuration)?.1,
+        },
+        b"ramp" => {
+            let channel = tokens.arg(parse_channel, InvalidChannel)?.1;
+            let from = tokens.arg(parse_value, InvalidValue)?.1;
+            let (at, to) = tokens.arg(parse_value, InvalidValue)?;
+            if core::mem::discriminant(&from) != core::mem::discriminant(&to) {
+                return Err(ParseError {
+                    position: at,
+                    kind: InvalidValue,
+                });
+            }
+            Step::Ramp {
+                channel,
+                from,
+                to,
+                us: tokens.arg(parse_duration, InvalidDuration)?.1,
+                steps: tokens.arg(parse_steps, InvalidSteps)?.1,
+            }
+        }
+        b"load" => Step::Load,
+        b"clear" => Step::Clear,
+        _ => {
+            return Err(ParseError {
+                position,
+                kind: UnknownCommand,
+            })
+        }
+    };
+    tokens.finish()?;
+    Ok(Some((position, step)))
+}
+
+fn parse_channel(s: &str) -> Option<Channel> {
+    match s {
+        "a" => Some(Channel::Index(0)),
+        "b" => Some(Channel::Index(1)),
+        "c" => Some(Channel::Index(2)),
+        "d" => Some(Channel::Index(3)),
+        "all" => Some(Channel::All),
+        _ => None,
+    }
+}
+
+fn parse_value(s: &str) -> Option<Value> {
+    if let Some(mv) = s.strip_suffix("mV") {
+        return parse_decimal(mv, 3).map(Value::Microvolts);
+    }
+    if let Some(v) = s.strip_suffix('V') {
+        return parse_decimal(v, 6).map(Value::Microvolts);
+    }
+    let code = match s.strip_prefix("0x") {
+        Some(hex) => u16::from_str_radix(hex, 16).ok()?,
+        None => s.parse().ok()?,
+    };
+    Some(Value::Code(code))
+}
+
+/// Parse a decimal number scaled by 10^`digits`, rejecting lost precision
+fn parse_decimal(s: &str, digits: usize) -> Option<i32> {
+    let (negative, s) = match s.strip_prefix('-') {
+        Some(s) => (true, s),
+        None => (false, s),
+    };
+    let (int, frac) = s.split_once('.').unwrap_or((s, ""));
+    let all_digits = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+    if int.is_empty() || frac.len() > digits || !all_digits(int) || !all_digits(frac) {
+        return None;
+    }
+    let mut value: i64 = int.parse().ok()?;
+    for i in 0..digits {
+        let digit = frac.as_bytes().get(i).map_or(0, |d| (d - b'0') as i64);
+        value = value.checked_mul(10)?.checked_add(digit)?;
+    }
+    i32::try_from(if negative { -value } else { value }).ok()
+}
+
+fn parse_range(s: &str) -> Option<OutputRange> {
+    match s {
+        "uni5" => Some(OutputRange::Unipolar5V),
+        "uni10" => Some(OutputRange::Unipolar10V),
+        "uni10.8" => Some(OutputRange::Unipolar10_8V),
+        "bi5" => Some(OutputRange::Bipolar5V),
+        "bi10" => Some(OutputRange::Bipolar10V),
+        "bi10.8" => Some(OutputRange::Bipolar10_8V),
+        _ => None,
+    }
+}
+
+fn parse_power(s: &str) -> Option<bool> {
+    match s {
+        "on" => Some(true),
+        "off" => Some(false),
+        _ => None,
+    }
+}
+
+fn parse_duration(s: &str) -> Option<u32> {
+    let (num, scale) = if let Some(num) = s.strip_suffix("us") {
+        (num, 1)
+    } else if let Some(num) = s.strip_suffix("ms") {
+        (num, 1_000)
+    } else {
+        (s.strip_suffix('s')?, 1_000_000)
+    };
+    if !num.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    num.parse::<u32>().ok()?.checked_mul(scale)
+}
+
+fn parse_steps(s: &str) -> Option<u16> {
+    s.parse().ok().filter(|&steps| steps > 0)
+}
+
+/// Plays back sequences
+pub struct Executor<DL> {
+    delay: DL,
+    vref: u32,
+    ranges: [Option<OutputRange>; 4],
+}
+
+impl<DL: DelayNs> Executor<DL> {
+    /// Create an executor assuming the default reference voltage and unknown
+    /// ranges
+    pub fn new(delay: DL) -> Self {
+        Executor {
+            delay,
+            vref: voltage::VREF_DEFAULT,
+            ranges: [None; 4],
+        }
+    }
+
+    /// Set the reference voltage in microvolts used to convert voltages
+    pub fn with_reference_microvolts(mut self, vref_uv: u32) -> Self {
+        self.vref = vref_uv;
+        self
+    }
+
+    /// Set the range of a channel that is already configured on the device.
+    /// Voltages can only be written to channels with a known range.
+    pub fn with_range(mut self, index: u8, range: OutputRange) -> Self {
+        if let Some(slot) = self.ranges.get_mut(index as usize) {
+            *slot = Some(range);
+        }
+        self
+    }
+
+    /// Return the delay
+    pub fn destroy(self) -> DL {
+        self.delay
+    }
+
+    /// Run every command of the sequence, stopping at the first error
+    pub fn run<D, DEV, E, CH, PCFG>(
+        &mut self,
+        dac: &mut D,
+        sequence: &Sequence<'_>,
+    ) -> Result<(), ExecError<E>>
+    where
+        D: Ad57xx<DEV, E, CH = CH, PCFG = PCFG>,
+        CH: Copy + TryFrom<Channel>,
+        u16: From<PCFG> + Into<PCFG>,
+        u8: From<CH> + From<Command<CH>>,
+    {
+        for (position, step) in sequence.steps() {
+            self.step(dac, step)
+                .map_err(|error| ExecError { position, error })?;
+        }
+        Ok(())
+    }
+
+    fn step<D, DEV, E, CH, PCFG>(&mut self, dac: &mut D, step: Step) -> Result<(), Error<E>>
+    where
+        D: Ad57xx<DEV, E, CH = CH, PCFG = PCFG>,
+        CH: Copy + TryFrom<Channel>,
+        u16: From<PCFG> + Into<PCFG>,
+        u8: From<CH> + From<Command<CH>>,
+    {
+        match step {
+            Step::Set { channel, value } => self.set(dac, channel, value)?,
+            Step::Range { channel, range } => {
+                dac.set_output_range(channel_of(channel)?, range)?;
+                for (index, _) in selected::<CH>(channel) {
+                    self.ranges[index] = Some(range);
+                }
+            }
+            Step::Power { channel, on } => {
+                channel_of::<CH, E>(channel)?;
+                let bits = |channel| {
+                    selected::<CH>(channel).fold(0u16, |m, (_, ch)| m | 1 << u8::from(ch))
+                };
+                // Channels not named by the step keep their state, the status
+                // flags are not writable
+                let pcfg = u16::from(dac.get_power_config()?) & bits(Channel::All);
+                let mask = bits(channel);
+                let pcfg = if on { pcfg | mask } else { pcfg & !mask };
+                dac.set_power_config(pcfg.into())?;
+            }
+            Step::Wait { us } => self.delay.delay_us(us),
+            Step::Ramp {
+                channel,
+                from,
+                to,
+                us,
+                steps,
+            } => {
+                for i in 0..=steps as i64 {
+                    let value = match (from, to) {
+                        (Value::Code(a), Value::Code(b)) => {
+                            Value::Code(lerp(a as i64, b as i64, i, steps) as u16)
+                        }
+                        (Value::Microvolts(a), Value::Microvolts(b)) => {
+                            Value::Microvolts(lerp(a as i64, b as i64, i, steps) as i32)
+                        }
+                        _ => return Err(Error::InvalidArgument),
+                    };
+                    self.set(dac, channel, value)?;
+                    if i < steps as i64 {
+                        self.delay.delay_us(us / steps as u32);
+                    }
+                }
+            }
+            Step::Load => dac.load_dacs()?,
+            Step::Clear => dac.clear_dacs()?,
+        }
+        Ok(())
+    }
+
+    fn set<D, DEV, E, CH, PCFG>(
+        &mut self,
+        dac: &mut D,
+        channel: Channel,
+        value: Value,
+    ) -> Result<(), Error<E>>
+    where
+        D: Ad57xx<DEV, E, CH = CH, PCFG = PCFG>,
+        CH: Copy + TryFrom<Channel>,
+        u16: From<PCFG> + Into<PCFG>,
+        u8: From<CH> + From<Command<CH>>,
+    {
+        let uv = match value {
+            Value::Code(code) => return dac.set_dac_output(channel_of(channel)?, code),
+            Value::Microvolts(uv) => uv,
+        };
+        channel_of::<CH, E>(channel)?;
+        for (index, ch) in selected::<CH>(channel) {
+            let code = self.ranges[index]
+                .and_then(|range| range.microvolts_to_code(uv, self.vref))
+                .ok_or(Error::InvalidArgument)?;
+            dac.set_dac_output(ch, code)?;
+        }
+        Ok(())
+    }
+}
+
+fn channel_of<CH: TryFrom<Channel>, E>(channel: Channel) -> Result<CH, Error<E>> {
+    CH::try_from(channel).map_err(|_| Error::InvalidArgument)
+}
+
+/// Individual channels selected by a channel, with their index
+fn selected<CH: TryFrom<Channel>>(channel: Channel) -> impl Iterator<Item = (usize, CH)> {
+    (0..4u8)
+        .filter(move |&i| channel == Channel::All || channel == Channel::Index(i))
+        .filter_map(|i| Some((i as usize, CH::try_from(Channel::Index(i)).ok()?)))
+}
+
+fn lerp(from: i64, to: i64, i: i64, steps: u16) -> i64 {
+    from + (to - from) * i / steps as i64
+}
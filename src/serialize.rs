@@ -0,0 +1,83 @@
+//! Serde support for the register bitfields
+//!
+//! The bitfields are serialized as their raw register value. Deserializing
+//! rejects values with bits set outside the defined fields.
+use serde::de::{Deserialize, Deserializer, Error, Unexpected};
+use serde::ser::{Serialize, Serializer};
+
+use crate::ad57x2::PowerConfigDual;
+use crate::ad57x4::PowerConfigQuad;
+use crate::Config;
+
+// Bits holding a field in the registers
+const CONFIG_MASK: u16 = 0x000F;
+const POWER_QUAD_MASK: u16 = 0x07AF;
+const POWER_DUAL_MASK: u16 = 0x02A5;
+const POWER_UP_MASK: u16 = 0x000F;
+const SCALE_MASK: u16 = 0x0FFF;
+
+fn masked<'de, D: Deserializer<'de>>(deserializer: D, mask: u16) -> Result<u16, D::Error> {
+    let value = u16::deserialize(deserializer)?;
+    if value & !mask != 0 {
+        return Err(reserved(value));
+    }
+    Ok(value)
+}
+
+fn reserved<E: Error>(value: impl Into<u64>) -> E {
+    E::invalid_value(
+        Unexpected::Unsigned(value.into()),
+        &"a value without reserved bits set",
+    )
+}
+
+/// Power-up bits of a [`DeviceState`](crate::state::DeviceState)
+pub(crate) fn power_up<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u16, D::Error> {
+    masked(deserializer, POWER_UP_MASK)
+}
+
+/// Semitone mask of a [`Scale::Custom`](crate::cv::Scale::Custom)
+pub(crate) fn scale_mask<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u16, D::Error> {
+    masked(deserializer, SCALE_MASK)
+}
+
+impl Serialize for Config {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        u8::from(*self).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Config {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        // Same width as serialized
+        let value = u8::deserialize(deserializer)?;
+        if value as u16 & !CONFIG_MASK != 0 {
+            return Err(reserved(value));
+        }
+        Ok(Config::from(value))
+    }
+}
+
+impl Serialize for PowerConfigQuad {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        u16::from(*self).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for PowerConfigQuad {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        masked(deserializer, POWER_QUAD_MASK).map(PowerConfigQuad::from)
+    }
+}
+
+impl Serialize for PowerConfigDual {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        u16::from(*self).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for PowerConfigDual {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        masked(deserializer, POWER_DUAL_MASK).map(PowerConfigDual::from)
+    }
+}
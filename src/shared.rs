@@ -0,0 +1,198 @@
+//! Device shared between interrupt handlers and the main loop
+//!
+//! [`SharedAd57xx`] keeps the driver in a `critical_section::Mutex` so it can
+//! be placed in a `static` and used from any context. Every operation holds
+//! the critical section for exactly one SPI frame, operations on all channels
+//! that need a frame per channel release it in between. This bounds the
+//! interrupt latency to the duration of a single 24 bit transfer.
+//!
+//! The driver has no const constructor, so the device is moved into a
+//! `static` at runtime, for example with the `static_cell` crate. The handles
+//! of a `&'static` device are `'static` themselves and can be handed to
+//! interrupt handlers.
+//!
+//! ```ignore
+//! static DAC: StaticCell<SharedAd57xx<Spi, marker::Ad57x4>> = StaticCell::new();
+//!
+//! let dac: &'static _ = DAC.init(SharedAd57xx::new(Ad57xxShared::new_ad57x4(spi)));
+//! let [pitch, gate, _, _] = dac.split();
+//! pitch.set_microvolts(1_000_000)?;
+//! // in an interrupt handler
+//! gate.set_code(0xFFFF)?;
+//! ```
+use core::cell::RefCell;
+
+use critical_section::Mutex;
+use embedded_hal::spi::SpiDevice;
+
+use crate::{frame, marker, private::Sealed, Ad57xxShared, Error, Function, OutputRange};
+
+/// A device behind a critical section mutex
+pub struct SharedAd57xx<DEV, IC> {
+    dac: Mutex<RefCell<Ad57xxShared<DEV, IC>>>,
+}
+
+impl<DEV, IC> SharedAd57xx<DEV, IC> {
+    /// Wrap a device
+    pub const fn new(dac: Ad57xxShared<DEV, IC>) -> Self {
+        SharedAd57xx {
+            dac: Mutex::new(RefCell::new(dac)),
+        }
+    }
+
+    /// Return the device
+    pub fn into_inner(self) -> Ad57xxShared<DEV, IC> {
+        self.dac.into_inner().into_inner()
+    }
+
+    /// Run a closure with exclusive access to the device.
+    ///
+    /// The critical section is held for the whole closure, keep it short.
+    /// Using the shared device from within the closure panics.
+    pub fn lock<R>(&self, f: impl FnOnce(&mut Ad57xxShared<DEV, IC>) -> R) -> R {
+        critical_section::with(|cs| f(&mut self.dac.borrow_ref_mut(cs)))
+    }
+}
+
+impl<DEV, IC, E> SharedAd57xx<DEV, IC>
+where
+    DEV: SpiDevice<Error = E>,
+    IC: Sealed,
+{
+    /// Handle to a single channel, or to all channels
+    pub fn channel(&self, chan: IC::CH) -> ChannelHandle<'_, DEV, IC> {
+        ChannelHandle { dac: self, chan }
+    }
+
+    /// Write a code to the DAC register of the channel(s)
+    pub fn set_dac_output(&self, chan: IC::CH, code: u16) -> Result<(), Error<E>> {
+        let addr: u8 = chan.into();
+        self.lock(|dac| dac.write_frame(&frame(0b000, addr, code)))
+    }
+
+    /// Write an output voltage in microvolts to the channel(s), see
+    /// [`Ad57xxShared::set_dac_microvolts`]. Takes one frame per channel.
+    pub fn set_dac_microvolts(&self, chan: IC::CH, uv: i32) -> Result<(), Error<E>> {
+        let frames = self
+            .lock(|dac| dac.microvolts_frames(chan.into(), uv, 16))
+            .ok_or(Error::InvalidArgument)?;
+        for payload in frames.iter().flatten() {
+            self.lock(|dac| dac.write_frame(payload))?;
+        }
+        Ok(())
+    }
+
+    /// Select the output range of the channel(s)
+    pub fn set_output_range(&self, chan: IC::CH, range: OutputRange) -> Result<(), Error<E>> {
+        if range == OutputRange::InvalidReadback {
+            return Err(Error::InvalidArgument);
+        }
+        let addr: u8 = chan.into();
+        self.lock(|dac| dac.write_frame(&frame(0b001, addr, range as u16)))
+    }
+
+    /// Power up or down the channel(s)
+    pub fn set_power(&self, chan: IC::CH, pwr: bool) -> Result<(), Error<E>> {
+        let addr: u8 = chan.into();
+        self.lock(|dac| dac.write_frame(&dac.power_frame(addr, pwr)))
+    }
+
+    /// Load the DAC registers of all channels
+    pub fn load_dacs(&self) -> Result<(), Error<E>> {
+        self.lock(|dac| dac.write_frame(&frame(0b011, Function::Load as u8, 0)))
+    }
+
+    /// Set the DAC registers of all channels to the clear code
+    pub fn clear_dacs(&self) -> Result<(), Error<E>> {
+        self.lock(|dac| dac.write_frame(&frame(0b011, Function::Clear as u8, 0)))
+    }
+
+    /// Last written DAC register value of a single channel
+    pub fn dac_output(&self, chan: IC::CH) -> Result<u16, Error<E>> {
+        let addr: u8 = chan.into();
+        if addr == 4 {
+            return Err(Error::InvalidArgument);
+        }
+        Ok(self.lock(|dac| dac.codes[addr as usize]))
+    }
+
+    /// Output voltage in microvolts of the last written DAC register value
+    pub fn dac_microvolts(&self, chan: IC::CH) -> Result<i32, Error<E>> {
+        self.lock(|dac| dac.dac_microvolts(chan))
+    }
+}
+
+impl<DEV, E> SharedAd57xx<DEV, marker::Ad57x4>
+where
+    DEV: SpiDevice<Error = E>,
+{
+    /// Handles to the channels A to D
+    pub fn split(&self) -> [ChannelHandle<'_, DEV, marker::Ad57x4>; 4] {
+        core::array::from_fn(|i| self.channel(marker::Ad57x4::CHANNELS[i]))
+    }
+}
+
+impl<DEV, E> SharedAd57xx<DEV, marker::Ad57x2>
+where
+    DEV: SpiDevice<Error = E>,
+{
+    /// Handles to the channels A and B
+    pub fn split(&self) -> [ChannelHandle<'_, DEV, marker::Ad57x2>; 2] {
+        core::array::from_fn(|i| self.channel(marker::Ad57x2::CHANNELS[i]))
+    }
+}
+
+/// Access to one channel of a shared device
+pub struct ChannelHandle<'a, DEV, IC: Sealed> {
+    dac: &'a SharedAd57xx<DEV, IC>,
+    chan: IC::CH,
+}
+
+impl<DEV, IC: Sealed> Clone for ChannelHandle<'_, DEV, IC> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<DEV, IC: Sealed> Copy for ChannelHandle<'_, DEV, IC> {}
+
+impl<DEV, IC, E> ChannelHandle<'_, DEV, IC>
+where
+    DEV: SpiDevice<Error = E>,
+    IC: Sealed,
+{
+    /// The channel of the handle
+    pub fn channel(&self) -> IC::CH {
+        self.chan
+    }
+
+    /// Write a code to the DAC register
+    pub fn set_code(&self, code: u16) -> Result<(), Error<E>> {
+        self.dac.set_dac_output(self.chan, code)
+    }
+
+    /// Write an output voltage in microvolts
+    pub fn set_microvolts(&self, uv: i32) -> Result<(), Error<E>> {
+        self.dac.set_dac_microvolts(self.chan, uv)
+    }
+
+    /// Select the output range
+    pub fn set_range(&self, range: OutputRange) -> Result<(), Error<E>> {
+        self.dac.set_output_range(self.chan, range)
+    }
+
+    /// Power the channel up or down
+    pub fn set_power(&self, pwr: bool) -> Result<(), Error<E>> {
+        self.dac.set_power(self.chan, pwr)
+    }
+
+    /// Last written DAC register value
+    pub fn code(&self) -> Result<u16, Error<E>> {
+        self.dac.dac_output(self.chan)
+    }
+
+    /// Output voltage in microvolts of the last written DAC register value
+    pub fn microvolts(&self) -> Result<i32, Error<E>> {
+        self.dac.dac_microvolts(self.chan)
+    }
+}
@@ -0,0 +1,177 @@
+//! Asynchronous device shared between tasks
+//!
+//! [`AsyncSharedAd57xx`] keeps an [`Ad57xxAsync`] behind an `embassy-sync`
+//! mutex, generic over the [`RawMutex`] flavour. Each operation locks the
+//! mutex for one frame, so tasks writing to different channels interleave
+//! frame by frame. Dropping an operation future at any point is safe, an
+//! interrupted frame is completed by the next operation on the device.
+//!
+//! ```ignore
+//! static DAC: AsyncSharedAd57xx<CriticalSectionRawMutex, Spi, marker::Ad57x4> = ...;
+//!
+//! #[embassy_executor::task]
+//! async fn lfo(chan: AsyncChannel<'static, CriticalSectionRawMutex, Spi, marker::Ad57x4>) {
+//!     loop {
+//!         chan.set_code(next_sample()).await.ok();
+//!     }
+//! }
+//! ```
+use embassy_sync::blocking_mutex::raw::RawMutex;
+use embassy_sync::mutex::{Mutex, MutexGuard};
+use embedded_hal_async::spi::SpiDevice;
+
+use crate::asynch::Ad57xxAsync;
+use crate::{marker, private::Sealed, Error, OutputRange};
+
+/// An asynchronous device behind a mutex
+pub struct AsyncSharedAd57xx<M: RawMutex, DEV, IC> {
+    dac: Mutex<M, Ad57xxAsync<DEV, IC>>,
+}
+
+impl<M: RawMutex, DEV, IC> AsyncSharedAd57xx<M, DEV, IC> {
+    /// Wrap a device
+    pub const fn new(dac: Ad57xxAsync<DEV, IC>) -> Self {
+        AsyncSharedAd57xx {
+            dac: Mutex::new(dac),
+        }
+    }
+
+    /// Return the device
+    pub fn into_inner(self) -> Ad57xxAsync<DEV, IC> {
+        self.dac.into_inner()
+    }
+
+    /// Exclusive access to the device until the guard is dropped
+    pub async fn lock(&self) -> MutexGuard<'_, M, Ad57xxAsync<DEV, IC>> {
+        self.dac.lock().await
+    }
+}
+
+impl<M, DEV, IC, E> AsyncSharedAd57xx<M, DEV, IC>
+where
+    M: RawMutex,
+    DEV: SpiDevice<Error = E>,
+    IC: Sealed,
+{
+    /// Handle to a single channel, or to all channels
+    pub fn channel(&self, chan: IC::CH) -> AsyncChannel<'_, M, DEV, IC> {
+        AsyncChannel { dac: self, chan }
+    }
+
+    /// Write a code to the DAC register of the channel(s)
+    pub async fn set_dac_output(&self, chan: IC::CH, code: u16) -> Result<(), Error<E>> {
+        self.lock().await.set_dac_output(chan, code).await
+    }
+
+    /// Write an output voltage in microvolts to the channel(s), see
+    /// [`Ad57xxAsync::set_dac_microvolts`]. The mutex is released between
+    /// the frames of the individual channels.
+    pub async fn set_dac_microvolts(&self, chan: IC::CH, uv: i32) -> Result<(), Error<E>> {
+        let frames = self.lock().await.microvolts_frames(chan, uv).await?;
+        for payload in frames.iter().flatten() {
+            self.lock().await.write_frame(payload).await?;
+        }
+        Ok(())
+    }
+
+    /// Select the output range of the channel(s)
+    pub async fn set_output_range(&self, chan: IC::CH, range: OutputRange) -> Result<(), Error<E>> {
+        self.lock().await.set_output_range(chan, range).await
+    }
+
+    /// Power up or down the channel(s)
+    pub async fn set_power(&self, chan: IC::CH, pwr: bool) -> Result<(), Error<E>> {
+        self.lock().await.set_power(chan, pwr).await
+    }
+
+    /// Load the DAC registers of all channels
+    pub async fn load_dacs(&self) -> Result<(), Error<E>> {
+        self.lock().await.load_dacs().await
+    }
+
+    /// Set the DAC registers of all channels to the clear code
+    pub async fn clear_dacs(&self) -> Result<(), Error<E>> {
+        self.lock().await.clear_dacs().await
+    }
+
+    /// Last written DAC register value of a single channel, completing an
+    /// interrupted write first
+    pub async fn dac_output(&self, chan: IC::CH) -> Result<u16, Error<E>> {
+        let mut dac = self.lock().await;
+        dac.flush().await?;
+        dac.dac_output(chan)
+    }
+}
+
+impl<M, DEV, E> AsyncSharedAd57xx<M, DEV, marker::Ad57x4>
+where
+    M: RawMutex,
+    DEV: SpiDevice<Error = E>,
+{
+    /// Handles to the channels A to D
+    pub fn split(&self) -> [AsyncChannel<'_, M, DEV, marker::Ad57x4>; 4] {
+        core::array::from_fn(|i| self.channel(marker::Ad57x4::CHANNELS[i]))
+    }
+}
+
+impl<M, DEV, E> AsyncSharedAd57xx<M, DEV, marker::Ad57x2>
+where
+    M: RawMutex,
+    DEV: SpiDevice<Error = E>,
+{
+    /// Handles to the channels A and B
+    pub fn split(&self) -> [AsyncChannel<'_, M, DEV, marker::Ad57x2>; 2] {
+        core::array::from_fn(|i| self.channel(marker::Ad57x2::CHANNELS[i]))
+    }
+}
+
+/// Asynchronous access to one channel of a shared device
+pub struct AsyncChannel<'a, M: RawMutex, DEV, IC: Sealed> {
+    dac: &'a AsyncSharedAd57xx<M, DEV, IC>,
+    chan: IC::CH,
+}
+
+impl<M: RawMutex, DEV, IC: Sealed> Clone for AsyncChannel<'_, M, DEV, IC> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<M: RawMutex, DEV, IC: Sealed> Copy for AsyncChannel<'_, M, DEV, IC> {}
+
+impl<M, DEV, IC, E> AsyncChannel<'_, M, DEV, IC>
+where
+    M: RawMutex,
+    DEV: SpiDevice<Error = E>,
+    IC: Sealed,
+{
+    /// The channel of the handle
+    pub fn channel(&self) -> IC::CH {
+        self.chan
+    }
+
+    /// Write a code to the DAC register
+    pub async fn set_code(&self, code: u16) -> Result<(), Error<E>> {
+        self.dac.set_dac_output(self.chan, code).await
+    }
+
+    /// Write an output voltage in microvolts
+    pub async fn set_microvolts(&self, uv: i32) -> Result<(), Error<E>> {
+        self.dac.set_dac_microvolts(self.chan, uv).await
+    }
+
+    /// Select the output range
+    pub async fn set_range(&self, range: OutputRange) -> Result<(), Error<E>> {
+        self.dac.set_output_range(self.chan, range).await
+    }
+
+    /// Power the channel up or down
+    pub async fn set_power(&self, pwr: bool) -> Result<(), Error<E>> {
+        self.dac.set_power(self.chan, pwr).await
+    }
+
+    /// Last written DAC register value
+    pub async fn code(&self) -> Result<u16, Error<E>> {
+        self.dac.dac_output(self.chan).await
+    }
+}
@@ -0,0 +1,184 @@
+//! Shutdown sequence and safe state on drop
+//!
+//! [`Ad57xxShared::shutdown`] parks the outputs at safe values, powers down
+//! every channel and optionally disables SDO. [`ShutdownGuard`] runs the
+//! sequence when it goes out of scope, also while unwinding from a panic.
+//!
+//! ```ignore
+//! let mut dac = ShutdownGuard::new(dac, Shutdown::default(), delay);
+//! dac.set_output_range(ChannelQuad::AllDacs, OutputRange::Bipolar10V)?;
+//! dac.set_power(ChannelQuad::AllDacs, true)?;
+//! run_test(&mut dac)?; // a panic here leaves the outputs powered down
+//! let (dac, delay) = dac.disarm();
+//! ```
+use core::ops::{Deref, DerefMut};
+
+use embedded_hal::delay::DelayNs;
+use embedded_hal::spi::SpiDevice;
+
+use crate::{frame, private::Sealed, Ad57xxShared, Error, Function};
+
+/// How to bring the outputs to a safe value before powering down
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Park {
+    /// Set the DAC registers to the clear code with the clear command
+    Clear,
+    /// Write a code per channel, index 0 is channel A, and load the DAC
+    /// registers
+    Codes([u16; 4]),
+    /// Step linearly from the last written codes to a code per channel.
+    /// The steps are spaced by the delay, not while holding the bus.
+    Ramp {
+        /// Final code per channel, index 0 is channel A
+        codes: [u16; 4],
+        /// Number of steps, at least 1
+        steps: u16,
+        /// Delay after each step in microseconds
+        step_us: u32,
+    },
+}
+
+/// Shutdown sequence
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Shutdown {
+    /// Safe values of the outputs
+    pub park: Park,
+    /// Disable the SDO output after powering down
+    pub disable_sdo: bool,
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Shutdown {
+            park: Park::Clear,
+            disable_sdo: false,
+        }
+    }
+}
+
+impl<DEV, IC, E> Ad57xxShared<DEV, IC>
+where
+    DEV: SpiDevice<Error = E>,
+    IC: Sealed,
+{
+    /// Park the outputs, power down all channels and optionally disable SDO.
+    /// The delay is only used between the steps of a ramp.
+    ///
+    /// Returns [`Error::InvalidArgument`] for a ramp without steps before
+    /// anything is written.
+    pub fn shutdown(&mut self, plan: &Shutdown, delay: &mut impl DelayNs) -> Result<(), Error<E>> {
+        let load = frame(0b011, Function::Load as u8, 0);
+        match plan.park {
+            Park::Clear => self.write_frame(&frame(0b011, Function::Clear as u8, 0))?,
+            Park::Codes(codes) => {
+                for (&addr, &code) in IC::ADDRESSES.iter().zip(codes.iter()) {
+                    self.write_frame(&frame(0b000, addr, code))?;
+                }
+                self.write_frame(&load)?;
+            }
+            Park::Ramp {
+                codes,
+                steps,
+                step_us,
+            } => {
+                if steps == 0 {
+                    return Err(Error::InvalidArgument);
+                }
+                // Interpolate in offset binary, where codes are monotonic in
+                // the output voltage also for two's complement coding
+                let from = self.codes;
+                for step in 1..=steps as i64 {
+                    for (&addr, &to) in IC::ADDRESSES.iter().zip(codes.iter()) {
+                        let range = self.ranges[addr as usize];
+                        let from = self.coding.offset_binary(range, from[addr as usize]) as i64;
+                        let to = self.coding.offset_binary(range, to) as i64;
+                        // 65535 * 65535 does not fit an i32
+                        let code = (from + (to - from) * step / steps as i64) as u16;
+                        let code = self.coding.offset_binary(range, code);
+                        self.write_frame(&frame(0b000, addr, code))?;
+                    }
+                    self.write_frame(&load)?;
+                    delay.delay_us(step_us);
+                }
+            }
+        }
+        self.write_frame(&frame(0b010, 0, self.pcfg & !IC::PU_MASK))?;
+        if plan.disable_sdo {
+            let cfg = u8::from(self.cfg.with_sdo_disable(true)) as u16;
+            self.write_frame(&frame(0b011, Function::Config as u8, cfg))?;
+        }
+        Ok(())
+    }
+}
+
+/// Device that runs a shutdown sequence when dropped
+///
+/// The guard dereferences to the device. Errors of the sequence on drop are
+/// ignored.
+pub struct ShutdownGuard<DEV, IC, D>
+where
+    DEV: SpiDevice,
+    IC: Sealed,
+    D: DelayNs,
+{
+    // Only `None` once the guard has been consumed
+    inner: Option<(Ad57xxShared<DEV, IC>, D)>,
+    plan: Shutdown,
+    armed: bool,
+}
+
+impl<DEV, IC, D, E> ShutdownGuard<DEV, IC, D>
+where
+    DEV: SpiDevice<Error = E>,
+    IC: Sealed,
+    D: DelayNs,
+{
+    /// Guard a device with a shutdown sequence
+    pub fn new(dac: Ad57xxShared<DEV, IC>, plan: Shutdown, delay: D) -> Self {
+        ShutdownGuard {
+            inner: Some((dac, delay)),
+            plan,
+            armed: true,
+        }
+    }
+
+    /// Return the device and the delay without shutting down
+    pub fn disarm(mut self) -> (Ad57xxShared<DEV, IC>, D) {
+        self.inner.take().expect("guard already consumed")
+    }
+
+    /// Run the shutdown sequence now instead of on drop.
+    ///
+    /// After success the guard is disarmed, on failure it stays armed and
+    /// dropping it runs the sequence again.
+    pub fn shutdown(&mut self) -> Result<(), Error<E>> {
+        let (dac, delay) = self.inner.as_mut().expect("guard already consumed");
+        dac.shutdown(&self.plan, delay)?;
+        self.armed = false;
+        Ok(())
+    }
+}
+
+impl<DEV: SpiDevice, IC: Sealed, D: DelayNs> Deref for ShutdownGuard<DEV, IC, D> {
+    type Target = Ad57xxShared<DEV, IC>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner.as_ref().expect("guard already consumed").0
+    }
+}
+
+impl<DEV: SpiDevice, IC: Sealed, D: DelayNs> DerefMut for ShutdownGuard<DEV, IC, D> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.inner.as_mut().expect("guard already consumed").0
+    }
+}
+
+impl<DEV: SpiDevice, IC: Sealed, D: DelayNs> Drop for ShutdownGuard<DEV, IC, D> {
+    fn drop(&mut self) {
+        if let (true, Some((dac, delay))) = (self.armed, &mut self.inner) {
+            let _ = dac.shutdown(&self.plan, delay);
+        }
+    }
+}
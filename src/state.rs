@@ -0,0 +1,142 @@
+//! Snapshot and restore of the complete device state
+//!
+//! ```ignore
+//! let profile = dac.snapshot()?;
+//! // ... run another test profile ...
+//! dac.restore(&profile)?;
+//! ```
+use embedded_hal::spi::SpiDevice;
+
+#[cfg(feature = "readback")]
+use crate::read_cmd;
+use crate::{frame, private::Sealed, Ad57xxShared, Config, Error, Function, OutputRange};
+
+/// Everything the DAC is doing: channel codes, ranges, power and configuration.
+///
+/// The per channel arrays are indexed by the channel address (`u8::from(chan)`),
+/// on dual channel parts only the entries of channel A (0) and B (2) are used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DeviceState {
+    /// DAC register values
+    pub codes: [u16; 4],
+    /// Output range of the channels
+    pub ranges: [OutputRange; 4],
+    /// Power-up bits of the power control register
+    #[cfg_attr(
+        feature = "serde",
+        serde(deserialize_with = "crate::serialize::power_up")
+    )]
+    pub power: u16,
+    /// Clear select, clamp, thermal shutdown and SDO settings
+    pub config: Config,
+}
+
+impl DeviceState {
+    /// Length of the serialized state in bytes
+    pub const SERIALIZED_LEN: usize = 15;
+
+    /// Serialize the state into a compact byte representation
+    pub fn to_bytes(&self) -> [u8; Self::SERIALIZED_LEN] {
+        let mut buf = [0u8; Self::SERIALIZED_LEN];
+        for (i, code) in self.codes.iter().enumerate() {
+            buf[2 * i..2 * i + 2].copy_from_slice(&code.to_be_bytes());
+        }
+        for (i, range) in self.ranges.iter().enumerate() {
+            buf[8 + i] = *range as u8;
+        }
+        buf[12..14].copy_from_slice(&self.power.to_be_bytes());
+        buf[14] = u8::from(self.config);
+        buf
+    }
+
+    /// Deserialize a state created by [`DeviceState::to_bytes`].
+    ///
+    /// Returns `None` if any of the ranges is invalid.
+    pub fn from_bytes(buf: &[u8; Self::SERIALIZED_LEN]) -> Option<Self> {
+        let mut codes = [0u16; 4];
+        let mut ranges = [OutputRange::Unipolar5V; 4];
+        for (i, (code, range)) in codes.iter_mut().zip(ranges.iter_mut()).enumerate() {
+            *code = u16::from_be_bytes([buf[2 * i], buf[2 * i + 1]]);
+            *range = match OutputRange::from(buf[8 + i] as u16) {
+                OutputRange::InvalidReadback => return None,
+                r => r,
+            };
+        }
+        Some(DeviceState {
+            codes,
+            ranges,
+            power: u16::from_be_bytes([buf[12], buf[13]]),
+            config: Config::from(buf[14]),
+        })
+    }
+}
+
+impl<DEV, IC, E> Ad57xxShared<DEV, IC>
+where
+    DEV: SpiDevice<Error = E>,
+    IC: Sealed,
+{
+    /// Capture the complete device state from the driver state
+    #[cfg(not(feature = "readback"))]
+    pub fn snapshot(&mut self) -> Result<DeviceState, Error<E>> {
+        Ok(self.cached_state())
+    }
+    /// Capture the complete device state by reading back every register.
+    ///
+    /// With SDO disabled nothing can be read and the driver state is
+    /// returned. The driver state is only updated if every register was read
+    /// back successfully.
+    #[cfg(feature = "readback")]
+    pub fn snapshot(&mut self) -> Result<DeviceState, Error<E>> {
+        if self.cfg.sdo_disable() {
+            return Ok(self.cached_state());
+        }
+        let (mut codes, mut ranges) = (self.codes, self.ranges);
+        for &addr in IC::ADDRESSES {
+            codes[addr as usize] = self.read_frame(read_cmd(0b000, addr))?;
+            let range = self.read_frame(read_cmd(0b001, addr))?;
+            ranges[addr as usize] = match OutputRange::from(range & 0b111) {
+                OutputRange::InvalidReadback => return Err(Error::ReadError),
+                range => range,
+            };
+        }
+        let pcfg = self.read_frame(read_cmd(0b010, 0))?;
+        let cfg = self.read_frame(read_cmd(0b011, Function::Config as u8))?;
+        self.codes = codes;
+        self.ranges = ranges;
+        self.pcfg = pcfg;
+        self.cfg = Config::from(cfg as u8);
+        Ok(self.cached_state())
+    }
+
+    /// Bring the device into a previously captured state.
+    ///
+    /// To minimise glitches all channels are powered down first, then the
+    /// ranges and codes are written and loaded before powering the channels
+    /// in `state` back up.
+    /// > After power up a timeout of 10us is required before the outputs settle.
+    pub fn restore(&mut self, state: &DeviceState) -> Result<(), Error<E>> {
+        let cfg = u8::from(state.config) as u16;
+        self.write_frame(&frame(0b011, Function::Config as u8, cfg))?;
+        // Only the power-up bits of the power control register are writable
+        self.write_frame(&frame(0b010, 0, 0x0000))?;
+        for &addr in IC::ADDRESSES {
+            let range = state.ranges[addr as usize] as u16;
+            self.write_frame(&frame(0b001, addr, range))?;
+            self.write_frame(&frame(0b000, addr, state.codes[addr as usize]))?;
+        }
+        self.write_frame(&frame(0b011, Function::Load as u8, 0x0000))?;
+        self.write_frame(&frame(0b010, 0, state.power & IC::PU_MASK))
+    }
+
+    pub(crate) fn cached_state(&self) -> DeviceState {
+        DeviceState {
+            codes: self.codes,
+            ranges: self.ranges,
+            power: self.pcfg & IC::PU_MASK,
+            config: self.cfg,
+        }
+    }
+}
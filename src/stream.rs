@@ -0,0 +1,178 @@
+//! Pre-encoded frames for DMA transfers
+//!
+//! A [`FrameEncoder`] renders samples for a set of channels into a byte
+//! buffer, one 24 bit frame per channel and sample, optionally followed by a
+//! load frame per sample. The buffers are meant to be handed to a SPI DMA
+//! transfer, a [`DoubleBuffer`] lets one half be refilled while the other one
+//! is transmitted.
+//!
+//! > The device latches a frame on the rising edge of SYNC. When several
+//! > frames are sent in one transfer the SPI peripheral has to pulse SYNC
+//! > after every 24 bits, for example with a hardware NSS pulse mode.
+//!
+//! ```ignore
+//! let encoder = FrameEncoder::new([ChannelQuad::DacA, ChannelQuad::DacB]).with_load(true);
+//! let mut buf = DoubleBuffer::<1536>::new();
+//! stream(&mut spi_bus, &encoder, &mut samples, &mut buf).await?;
+//! ```
+use core::future::{poll_fn, Future};
+use core::pin::pin;
+
+use embedded_hal_async::spi::SpiBus;
+
+use crate::{frame, Function};
+
+/// Length of a frame in bytes
+pub const FRAME_LEN: usize = 3;
+
+/// Encodes samples of `N` channels into frames
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct FrameEncoder<CH, const N: usize> {
+    channels: [CH; N],
+    load: bool,
+}
+
+impl<CH: Copy + Into<u8>, const N: usize> FrameEncoder<CH, N> {
+    /// Create an encoder writing the samples to the DAC registers of the
+    /// channels, in the given order
+    pub fn new(channels: [CH; N]) -> Self {
+        FrameEncoder {
+            channels,
+            load: false,
+        }
+    }
+
+    /// Follow every sample with a load frame, updating all outputs at once
+    pub fn with_load(mut self, load: bool) -> Self {
+        self.load = load;
+        self
+    }
+
+    /// Number of bytes a sample of all channels is encoded in
+    pub fn sample_len(&self) -> usize {
+        (N + self.load as usize) * FRAME_LEN
+    }
+
+    /// Encode samples until the buffer or the samples run out and return the
+    /// number of bytes written. Only complete samples are written, samples
+    /// that do not fit are left in the iterator.
+    pub fn encode(&self, samples: &mut impl Iterator<Item = [u16; N]>, buf: &mut [u8]) -> usize {
+        let mut len = 0;
+        while buf.len() - len >= self.sample_len() {
+            let Some(sample) = samples.next() else {
+                break;
+            };
+            for (chan, code) in self.channels.iter().zip(sample) {
+                buf[len..len + FRAME_LEN].copy_from_slice(&frame(0b000, (*chan).into(), code));
+                len += FRAME_LEN;
+            }
+            if self.load {
+                buf[len..len + FRAME_LEN].copy_from_slice(&frame(0b011, Function::Load as u8, 0));
+                len += FRAME_LEN;
+            }
+        }
+        len
+    }
+}
+
+/// Two buffers of `LEN` bytes, one being transmitted while the other one is
+/// refilled
+#[derive(Debug, Clone)]
+pub struct DoubleBuffer<const LEN: usize> {
+    bufs: [[u8; LEN]; 2],
+    lens: [usize; 2],
+    front: usize,
+}
+
+impl<const LEN: usize> Default for DoubleBuffer<LEN> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const LEN: usize> DoubleBuffer<LEN> {
+    /// Create two empty buffers
+    pub const fn new() -> Self {
+        DoubleBuffer {
+            bufs: [[0; LEN]; 2],
+            lens: [0; 2],
+            front: 0,
+        }
+    }
+
+    /// The filled part of the buffer to transmit
+    pub fn front(&self) -> &[u8] {
+        &self.bufs[self.front][..self.lens[self.front]]
+    }
+
+    /// Refill the back buffer and return the number of bytes written
+    pub fn refill<CH: Copy + Into<u8>, const N: usize>(
+        &mut self,
+        encoder: &FrameEncoder<CH, N>,
+        samples: &mut impl Iterator<Item = [u16; N]>,
+    ) -> usize {
+        let back = 1 - self.front;
+        self.lens[back] = encoder.encode(samples, &mut self.bufs[back]);
+        self.lens[back]
+    }
+
+    /// Make the back buffer the one to transmit
+    pub fn swap(&mut self) {
+        self.front = 1 - self.front;
+    }
+
+    /// The filled part of the front buffer and the whole back buffer, for
+    /// refilling the back buffer by other means while the front is in use.
+    /// Record the refilled length with [`DoubleBuffer::set_back_len`].
+    pub fn split(&mut self) -> (&[u8], &mut [u8; LEN]) {
+        let [a, b] = &mut self.bufs;
+        let len = self.lens[self.front];
+        match self.front {
+            0 => (&a[..len], b),
+            _ => (&b[..len], a),
+        }
+    }
+
+    /// Set the filled length of the back buffer, clamped to its size
+    pub fn set_back_len(&mut self, len: usize) {
+        self.lens[1 - self.front] = len.min(LEN);
+    }
+}
+
+/// Stream samples to the bus until they run out.
+///
+/// The next buffer is encoded while the previous one is transmitted. Every
+/// call to [`SpiBus::write`] transfers a complete buffer.
+pub async fn stream<BUS, CH, const N: usize, const LEN: usize>(
+    bus: &mut BUS,
+    encoder: &FrameEncoder<CH, N>,
+    samples: &mut impl Iterator<Item = [u16; N]>,
+    buf: &mut DoubleBuffer<LEN>,
+) -> Result<(), BUS::Error>
+where
+    BUS: SpiBus,
+    CH: Copy + Into<u8>,
+{
+    buf.refill(encoder, samples);
+    buf.swap();
+    while !buf.front().is_empty() {
+        let mut refilled = None;
+        {
+            let (front, back) = buf.split();
+            let mut write = pin!(bus.write(front));
+            // Encode the back buffer once the transfer of the front has started
+            poll_fn(|cx| {
+                let poll = write.as_mut().poll(cx);
+                if refilled.is_none() {
+                    refilled = Some(encoder.encode(samples, back));
+                }
+                poll
+            })
+            .await?;
+        }
+        buf.set_back_len(refilled.unwrap_or(0));
+        buf.swap();
+    }
+    bus.flush().await
+}
@@ -0,0 +1,163 @@
+//! Conversion between output voltages and DAC codes
+//!
+//! All conversions go through the gain and offset of the [`OutputRange`] and
+//! the reference voltage. The integer microvolt interface is the basis of the
+//! other ones and does not need an FPU. With the `fixed` feature voltages can
+//! be given as [`I16F16`](fixed::types::I16F16) volts, with the `uom` feature
+//! as [`ElectricPotential`](uom::si::f32::ElectricPotential).
+//!
+//! ```ignore
+//! dac.set_output_range(ChannelQuad::DacA, OutputRange::Bipolar10V)?;
+//! dac.set_dac_microvolts(ChannelQuad::DacA, -2_500_000)?;
+//! ```
+use embedded_hal::spi::SpiDevice;
+
+use crate::{private::Sealed, Ad57xxShared, Error, OutputRange};
+
+/// Reference voltage the output ranges are specified for, in microvolts
+pub const VREF_DEFAULT: u32 = 2_500_000;
+
+// Output levels are expressed relative to the reference, scaled by 100 * 2^16
+const LEVEL_SCALE: i64 = 100 << 16;
+
+impl OutputRange {
+    /// Offset binary code for an output voltage in microvolts.
+    ///
+    /// Returns `None` if the voltage is outside the range or the range is
+    /// invalid. Positive full scale itself is accepted and mapped to the
+    /// highest code.
+    pub fn microvolts_to_code(&self, uv: i32, vref_uv: u32) -> Option<u16> {
+        if vref_uv == 0 {
+            return None;
+        }
+        let level = (uv as i64 * LEVEL_SCALE).div_euclid(vref_uv as i64);
+        match self.unclamped_code(level)? {
+            code @ 0..=0xFFFF => Some(code as u16),
+            0x10000 => Some(0xFFFF),
+            _ => None,
+        }
+    }
+
+    /// Output voltage in microvolts of an offset binary code
+    pub fn code_to_microvolts(&self, code: u16, vref_uv: u32) -> i32 {
+        (self.level(code) * vref_uv as i64).div_euclid(LEVEL_SCALE) as i32
+    }
+
+    /// Offset binary code for an output voltage in volts, see
+    /// [`OutputRange::microvolts_to_code`].
+    pub fn volts_to_code(&self, volts: f32, vref: f32) -> Option<u16> {
+        let vref = u32::try_from(to_microvolts(vref)?).ok()?;
+        self.microvolts_to_code(to_microvolts(volts)?, vref)
+    }
+
+    /// Output voltage in volts of an offset binary code
+    pub fn code_to_volts(&self, code: u16, vref: f32) -> f32 {
+        self.level(code) as f32 / LEVEL_SCALE as f32 * vref
+    }
+
+    /// Offset binary code for an output voltage in fixed point volts, see
+    /// [`OutputRange::microvolts_to_code`].
+    #[cfg(feature = "fixed")]
+    pub fn fixed_to_code(
+        &self,
+        volts: fixed::types::I16F16,
+        vref: fixed::types::I16F16,
+    ) -> Option<u16> {
+        let vref = u32::try_from(fixed_to_microvolts(vref)?).ok()?;
+        self.microvolts_to_code(fixed_to_microvolts(volts)?, vref)
+    }
+
+    /// Output voltage in fixed point volts of an offset binary code
+    #[cfg(feature = "fixed")]
+    pub fn code_to_fixed(&self, code: u16, vref: fixed::types::I16F16) -> fixed::types::I16F16 {
+        let bits = (self.level(code) * vref.to_bits() as i64).div_euclid(LEVEL_SCALE);
+        fixed::types::I16F16::from_bits(bits as i32)
+    }
+}
+
+/// Round a voltage in volts to microvolts
+fn to_microvolts(volts: f32) -> Option<i32> {
+    let uv = volts * 1e6;
+    if !(i32::MIN as f32..=i32::MAX as f32).contains(&uv) {
+        return None;
+    }
+    Some(if uv < 0.0 { uv - 0.5 } else { uv + 0.5 } as i32)
+}
+
+/// Round a fixed point voltage to microvolts, `None` if it does not fit
+#[cfg(feature = "fixed")]
+fn fixed_to_microvolts(volts: fixed::types::I16F16) -> Option<i32> {
+    i32::try_from((volts.to_bits() as i64 * 1_000_000 + (1 << 15)) >> 16).ok()
+}
+
+impl<DEV, IC, E> Ad57xxShared<DEV, IC>
+where
+    DEV: SpiDevice<Error = E>,
+    IC: Sealed,
+{
+    /// Set the reference voltage in microvolts, 2.5V by default
+    pub fn set_reference_microvolts(&mut self, vref_uv: u32) {
+        self.vref = vref_uv;
+    }
+
+    /// Get the reference voltage in microvolts
+    pub fn reference_microvolts(&self) -> u32 {
+        self.vref
+    }
+
+    /// Write the code for an output voltage in microvolts to the DAC register
+    /// of the channel(s), using the last written range and the coding mode.
+    ///
+    /// Returns [`Error::InvalidArgument`] if the voltage is outside the range
+    /// of any of the selected channels.
+    pub fn set_dac_microvolts(&mut self, chan: IC::CH, uv: i32) -> Result<(), Error<E>> {
+        let frames = self
+            .microvolts_frames(chan.into(), uv, 16)
+            .ok_or(Error::InvalidArgument)?;
+        for payload in frames.iter().flatten() {
+            self.write_frame(payload)?;
+        }
+        Ok(())
+    }
+
+    /// Output voltage in microvolts of the last written DAC register value
+    pub fn dac_microvolts(&self, chan: IC::CH) -> Result<i32, Error<E>> {
+        let addr: u8 = chan.into();
+        if addr == 4 {
+            return Err(Error::InvalidArgument);
+        }
+        let range = self.ranges[addr as usize];
+        let code = self.coding.offset_binary(range, self.codes[addr as usize]);
+        Ok(range.code_to_microvolts(code, self.vref))
+    }
+
+    /// Write the code for an output voltage in volts, see
+    /// [`Ad57xxShared::set_dac_microvolts`].
+    pub fn set_dac_volts(&mut self, chan: IC::CH, volts: f32) -> Result<(), Error<E>> {
+        let uv = to_microvolts(volts).ok_or(Error::InvalidArgument)?;
+        self.set_dac_microvolts(chan, uv)
+    }
+
+    /// Write the code for an output voltage in fixed point volts, see
+    /// [`Ad57xxShared::set_dac_microvolts`].
+    #[cfg(feature = "fixed")]
+    pub fn set_dac_fixed(
+        &mut self,
+        chan: IC::CH,
+        volts: fixed::types::I16F16,
+    ) -> Result<(), Error<E>> {
+        let uv = fixed_to_microvolts(volts).ok_or(Error::InvalidArgument)?;
+        self.set_dac_microvolts(chan, uv)
+    }
+
+    /// Write the code for an output voltage, see
+    /// [`Ad57xxShared::set_dac_microvolts`].
+    #[cfg(feature = "uom")]
+    pub fn set_dac_potential(
+        &mut self,
+        chan: IC::CH,
+        potential: uom::si::f32::ElectricPotential,
+    ) -> Result<(), Error<E>> {
+        self.set_dac_volts(chan, potential.get::<uom::si::electric_potential::volt>())
+    }
+}
@@ -0,0 +1,211 @@
+//! Safe state on communication loss
+//!
+//! A [`Supervisor`] owns the device and expects to be fed by the control
+//! task. It counts ticks, typically from a periodic timer interrupt, and when
+//! no [`Supervisor::feed`] arrived within the timeout it drives the outputs
+//! to a [`SafeState`] and latches a fault. While the fault is latched the
+//! device is not accessible, it has to be acknowledged explicitly.
+//!
+//! ```ignore
+//! let mut sup = Supervisor::new(dac, 100, SafeState::codes([0x8000; 4]));
+//! // control task, on every valid host message
+//! sup.feed();
+//! sup.dac().ok_or(Faulted)?.set_dac_output(ChannelQuad::DacA, code)?;
+//! // 1ms timer interrupt
+//! if sup.tick()? == Status::Tripped {
+//!     defmt::error!("host link lost");
+//! }
+//! ```
+use core::convert::Infallible;
+
+use embedded_hal::digital::{ErrorType, OutputPin};
+use embedded_hal::spi::SpiDevice;
+
+use crate::{frame, private::Sealed, Ad57xxShared, Error, Function};
+
+/// What to do when the supervisor trips
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SafeState<CLR> {
+    /// Write a code per channel, index 0 is channel A, and load the DAC
+    /// registers
+    Codes([u16; 4]),
+    /// Set the DAC registers to the clear code with the clear command
+    Clear,
+    /// Pulse the ~CLR pin, which has to idle high. This works without SPI.
+    ClearPin(CLR),
+}
+
+impl SafeState<NoPin> {
+    /// Write a code per channel, index 0 is channel A, and load the DAC
+    /// registers
+    pub const fn codes(codes: [u16; 4]) -> Self {
+        SafeState::Codes(codes)
+    }
+
+    /// Use the clear command
+    pub const fn clear() -> Self {
+        SafeState::Clear
+    }
+}
+
+/// Placeholder for a safe state without ~CLR pin
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct NoPin;
+
+impl ErrorType for NoPin {
+    type Error = Infallible;
+}
+
+impl OutputPin for NoPin {
+    fn set_low(&mut self) -> Result<(), Infallible> {
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Infallible> {
+        Ok(())
+    }
+}
+
+/// State of the supervisor after a tick
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Status {
+    /// Fed in time
+    Running,
+    /// The timeout expired during this tick, the safe state was applied
+    Tripped,
+    /// A fault is latched and waits for acknowledgement
+    Faulted,
+}
+
+/// Errors of the supervisor
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SupervisorError<E, P> {
+    /// Applying the safe state through SPI failed, it is retried on the next
+    /// tick
+    Dac(Error<E>),
+    /// Driving the ~CLR pin failed, it is retried on the next tick
+    Clr(P),
+}
+
+/// Watchdog driving the device to a safe state
+pub struct Supervisor<DEV, IC, CLR> {
+    dac: Ad57xxShared<DEV, IC>,
+    safe: SafeState<CLR>,
+    timeout: u32,
+    elapsed: u32,
+    faulted: bool,
+    // The safe state has been applied since the fault was latched
+    applied: bool,
+}
+
+impl<DEV, IC, CLR> Supervisor<DEV, IC, CLR> {
+    /// Supervise a device with a timeout in ticks
+    pub fn new(dac: Ad57xxShared<DEV, IC>, timeout: u32, safe: SafeState<CLR>) -> Self {
+        Supervisor {
+            dac,
+            safe,
+            timeout,
+            elapsed: 0,
+            faulted: false,
+            applied: false,
+        }
+    }
+
+    /// Return the device and the safe state
+    pub fn destroy(self) -> (Ad57xxShared<DEV, IC>, SafeState<CLR>) {
+        (self.dac, self.safe)
+    }
+
+    /// Restart the timeout. A latched fault is not cleared.
+    pub fn feed(&mut self) {
+        self.elapsed = 0;
+    }
+
+    /// Ticks since the last feed
+    pub fn elapsed(&self) -> u32 {
+        self.elapsed
+    }
+
+    /// Returns true while a fault is latched
+    pub fn is_faulted(&self) -> bool {
+        self.faulted
+    }
+
+    /// Clear a latched fault and restart the timeout. Returns true if a fault
+    /// was latched. The outputs stay in the safe state until they are
+    /// written again.
+    pub fn acknowledge(&mut self) -> bool {
+        let faulted = self.faulted;
+        self.faulted = false;
+        self.applied = false;
+        self.elapsed = 0;
+        faulted
+    }
+
+    /// The device, `None` while a fault is latched
+    pub fn dac(&mut self) -> Option<&mut Ad57xxShared<DEV, IC>> {
+        (!self.faulted).then_some(&mut self.dac)
+    }
+}
+
+impl<DEV, IC, CLR, E, P> Supervisor<DEV, IC, CLR>
+where
+    DEV: SpiDevice<Error = E>,
+    IC: Sealed,
+    CLR: OutputPin<Error = P>,
+{
+    /// Advance the timeout by one tick, tripping when it expires. A safe
+    /// state that could not be applied is retried on every tick.
+    pub fn tick(&mut self) -> Result<Status, SupervisorError<E, P>> {
+        if self.faulted {
+            if !self.applied {
+                self.apply()?;
+            }
+            return Ok(Status::Faulted);
+        }
+        self.elapsed = self.elapsed.saturating_add(1);
+        if self.elapsed < self.timeout {
+            return Ok(Status::Running);
+        }
+        self.trip()?;
+        Ok(Status::Tripped)
+    }
+
+    /// Latch a fault and apply the safe state immediately
+    pub fn trip(&mut self) -> Result<(), SupervisorError<E, P>> {
+        if !self.faulted {
+            self.faulted = true;
+            self.applied = false;
+        }
+        self.apply()
+    }
+
+    fn apply(&mut self) -> Result<(), SupervisorError<E, P>> {
+        let clear = frame(0b011, Function::Clear as u8, 0);
+        match &mut self.safe {
+            SafeState::Codes(codes) => {
+                for (&addr, &code) in IC::ADDRESSES.iter().zip(codes.iter()) {
+                    self.dac
+                        .write_frame(&frame(0b000, addr, code))
+                        .map_err(SupervisorError::Dac)?;
+                }
+                self.dac
+                    .write_frame(&frame(0b011, Function::Load as u8, 0))
+                    .map_err(SupervisorError::Dac)?;
+            }
+            SafeState::Clear => self.dac.write_frame(&clear).map_err(SupervisorError::Dac)?,
+            SafeState::ClearPin(pin) => {
+                pin.set_low().map_err(SupervisorError::Clr)?;
+                pin.set_high().map_err(SupervisorError::Clr)?;
+                // The pin has the effect of the clear command
+                self.dac.track(&clear);
+            }
+        }
+        self.applied = true;
+        Ok(())
+    }
+}
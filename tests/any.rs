@@ -0,0 +1,91 @@
+use ad57xx::any::{AnyAd57xx, Variant};
+use ad57xx::model::Model;
+use ad57xx::sequence::Channel;
+use ad57xx::{Error, OutputRange};
+
+mod common;
+use common::Recorder;
+
+#[cfg(feature = "readback")]
+#[test]
+fn probe() {
+    use ad57xx::any::probe;
+    use ad57xx::{Ad57xx, Ad57xxShared, Config};
+
+    let mut quad = Model::ad57x4();
+    assert_eq!(probe(&mut quad, 16).unwrap(), Variant::Ad5754);
+    // The probed register is restored
+    assert_eq!(quad.range(1), Some(OutputRange::Unipolar5V));
+
+    let mut dual = Model::ad57x2();
+    assert_eq!(probe(&mut dual, 12).unwrap(), Variant::Ad5722);
+    assert!(matches!(probe(&mut dual, 13), Err(Error::InvalidArgument)));
+
+    let mut dac = Ad57xxShared::new_ad57x2(dual);
+    dac.set_config(Config::default().with_sdo_disable(true))
+        .unwrap();
+    let mut dual = dac.destroy();
+    assert!(matches!(probe(&mut dual, 16), Err(Error::ReadError)));
+}
+
+#[test]
+fn runtime_channels() {
+    let mut dac = AnyAd57xx::new(Model::ad57x2(), Variant::Ad5752);
+    assert_eq!(dac.channel_count(), 2);
+    dac.set_code(Channel::Index(1), 0x1234).unwrap();
+    assert!(matches!(
+        dac.set_code(Channel::Index(2), 0),
+        Err(Error::InvalidArgument)
+    ));
+    assert!(matches!(
+        dac.code(Channel::All),
+        Err(Error::InvalidArgument)
+    ));
+    assert_eq!(dac.code(Channel::Index(1)).unwrap(), 0x1234);
+    dac.set_range(Channel::All, OutputRange::Bipolar5V).unwrap();
+    assert_eq!(
+        dac.range(Channel::Index(0)).unwrap(),
+        OutputRange::Bipolar5V
+    );
+    // Channel B of the dual part is at address 2
+    let model = dac.destroy();
+    assert_eq!(model.dac_register(2), Some(0x1234));
+
+    let mut dac = AnyAd57xx::new(Model::ad57x4(), Variant::Ad5734);
+    dac.set_power(Channel::Index(3), true).unwrap();
+    assert_eq!(dac.destroy().power() & 0xF, 0b1000);
+}
+
+#[test]
+fn resolution() {
+    let mut dac = AnyAd57xx::new(Recorder::default(), Variant::Ad5724);
+    assert_eq!(dac.resolution(), 12);
+    dac.set_code(Channel::Index(0), 0xFFF).unwrap();
+    assert!(matches!(
+        dac.set_code(Channel::Index(0), 0x1000),
+        Err(Error::InvalidArgument)
+    ));
+    assert_eq!(dac.code(Channel::Index(0)).unwrap(), 0xFFF);
+    // 1mV is 0.82 LSB of 1.22mV on the 0V to 5V range
+    dac.set_microvolts(Channel::Index(1), 1_000).unwrap();
+    assert_eq!(dac.code(Channel::Index(1)).unwrap(), 1);
+    // Full scale does not round beyond the highest code
+    dac.set_microvolts(Channel::Index(2), 5_000_000).unwrap();
+    assert_eq!(
+        dac.destroy().frames,
+        [[0x00, 0xFF, 0xF0], [0x01, 0x00, 0x10], [0x02, 0xFF, 0xF0]]
+    );
+}
+
+#[test]
+fn all_channels_rejected_as_a_whole() {
+    let mut dac = AnyAd57xx::new(Recorder::default(), Variant::Ad5722);
+    dac.set_range(Channel::Index(0), OutputRange::Bipolar5V)
+        .unwrap();
+    // Negative voltages are valid for channel A only, nothing is written
+    assert!(matches!(
+        dac.set_microvolts(Channel::All, -1_000_000),
+        Err(Error::InvalidArgument)
+    ));
+    assert_eq!(dac.destroy().frames, [[0x08, 0x00, 0x03]]);
+}
@@ -0,0 +1,170 @@
+use std::future::Future;
+use std::pin::pin;
+use std::task::{Context, Waker};
+
+use ad57xx::ad57x2::ChannelDual;
+use ad57xx::ad57x4::ChannelQuad;
+use ad57xx::asynch::Ad57xxAsync;
+use ad57xx::marker;
+use ad57xx::model::Model;
+use ad57xx::{Error, OutputRange};
+use embassy_futures::block_on;
+use embedded_hal::spi::{ErrorType, Operation};
+
+/// Model whose transactions do not complete on their first poll
+struct Stalling {
+    model: Model<marker::Ad57x4>,
+}
+
+impl ErrorType for Stalling {
+    type Error = core::convert::Infallible;
+}
+
+impl embedded_hal_async::spi::SpiDevice for Stalling {
+    async fn transaction(
+        &mut self,
+        operations: &mut [Operation<'_, u8>],
+    ) -> Result<(), Self::Error> {
+        embassy_futures::yield_now().await;
+        embedded_hal::spi::SpiDevice::transaction(&mut self.model, operations)
+    }
+}
+
+/// Poll a future once and drop it
+fn poll_once_and_drop<F: Future>(fut: F) -> bool {
+    let mut cx = Context::from_waker(Waker::noop());
+    pin!(fut).poll(&mut cx).is_ready()
+}
+
+#[test]
+fn writes() {
+    let mut dac = Ad57xxAsync::new_ad57x2(Model::ad57x2());
+    block_on(async {
+        dac.set_output_range(ChannelDual::AllDacs, OutputRange::Bipolar5V)
+            .await
+            .unwrap();
+        dac.set_power(ChannelDual::DacB, true).await.unwrap();
+        dac.set_dac_microvolts(ChannelDual::DacB, 2_500_000)
+            .await
+            .unwrap();
+    });
+    assert_eq!(dac.dac_output(ChannelDual::DacB).unwrap(), 0xC000);
+    assert_eq!(dac.dac_microvolts(ChannelDual::DacB).unwrap(), 2_500_000);
+    let model = dac.destroy();
+    assert_eq!(model.power(), 0b0100);
+    assert_eq!(model.output(2), Some(0xC000));
+}
+
+#[test]
+fn dropped_write_is_completed() {
+    let mut dac = Ad57xxAsync::new_ad57x4(Stalling {
+        model: Model::ad57x4(),
+    });
+    assert!(!poll_once_and_drop(
+        dac.set_dac_output(ChannelQuad::DacC, 0x1234)
+    ));
+    // The cache is not updated by an interrupted write
+    assert!(dac.is_pending());
+    assert_eq!(dac.dac_output(ChannelQuad::DacC).unwrap(), 0);
+
+    // The next write completes the interrupted one first
+    block_on(dac.set_dac_output(ChannelQuad::DacD, 0x4321)).unwrap();
+    assert!(!dac.is_pending());
+    assert_eq!(dac.dac_output(ChannelQuad::DacC).unwrap(), 0x1234);
+    let model = dac.destroy().model;
+    assert_eq!(model.frames(), 2);
+    assert_eq!(model.dac_register(2), Some(0x1234));
+    assert_eq!(model.dac_register(3), Some(0x4321));
+}
+
+#[test]
+fn dropped_cache_dependent_writes_are_completed() {
+    let mut dac = Ad57xxAsync::new_ad57x4(Stalling {
+        model: Model::ad57x4(),
+    });
+    assert!(!poll_once_and_drop(dac.set_power(ChannelQuad::DacA, true)));
+    block_on(dac.set_power(ChannelQuad::DacB, true)).unwrap();
+
+    // The voltage is converted with the range of the interrupted write
+    assert!(!poll_once_and_drop(
+        dac.set_output_range(ChannelQuad::DacC, OutputRange::Bipolar5V)
+    ));
+    block_on(dac.set_dac_microvolts(ChannelQuad::DacC, 0)).unwrap();
+    let model = dac.destroy().model;
+    assert_eq!(model.power() & 0xF, 0b0011);
+    assert_eq!(model.dac_register(2), Some(0x8000));
+}
+
+#[test]
+fn voltage_rejected_as_a_whole() {
+    let mut dac = Ad57xxAsync::new_ad57x2(Model::ad57x2());
+    block_on(async {
+        dac.set_output_range(ChannelDual::DacA, OutputRange::Bipolar5V)
+            .await
+            .unwrap();
+        // Channel B is unipolar, channel A is not written either
+        assert!(matches!(
+            dac.set_dac_microvolts(ChannelDual::AllDacs, -1_000_000)
+                .await,
+            Err(Error::InvalidArgument)
+        ));
+    });
+    assert_eq!(dac.destroy().frames(), 1);
+
+    #[cfg(feature = "embassy-sync")]
+    {
+        use ad57xx::shared_async::AsyncSharedAd57xx;
+        use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+
+        let shared: AsyncSharedAd57xx<NoopRawMutex, _, _> =
+            AsyncSharedAd57xx::new(Ad57xxAsync::new_ad57x2(Model::ad57x2()));
+        block_on(async {
+            shared
+                .set_output_range(ChannelDual::DacA, OutputRange::Bipolar5V)
+                .await
+                .unwrap();
+            assert!(matches!(
+                shared
+                    .set_dac_microvolts(ChannelDual::AllDacs, -1_000_000)
+                    .await,
+                Err(Error::InvalidArgument)
+            ));
+        });
+        assert_eq!(shared.into_inner().destroy().frames(), 1);
+    }
+}
+
+#[cfg(feature = "embassy-sync")]
+async fn ramp<M: embassy_sync::blocking_mutex::raw::RawMutex>(
+    chan: ad57xx::shared_async::AsyncChannel<'_, M, Stalling, marker::Ad57x4>,
+    step: u16,
+) {
+    for i in 0..10u16 {
+        chan.set_code(i * step).await.unwrap();
+    }
+}
+
+#[cfg(feature = "embassy-sync")]
+#[test]
+fn shared_channels() {
+    use ad57xx::shared_async::AsyncSharedAd57xx;
+    use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+
+    let shared: AsyncSharedAd57xx<NoopRawMutex, _, _> =
+        AsyncSharedAd57xx::new(Ad57xxAsync::new_ad57x4(Stalling {
+            model: Model::ad57x4(),
+        }));
+    let [a, b, _, d] = shared.split();
+    block_on(async {
+        shared.set_power(ChannelQuad::AllDacs, true).await.unwrap();
+        embassy_futures::join::join3(ramp(a, 1), ramp(b, 2), ramp(d, 3)).await;
+        // A cancelled write is completed by the next user of the device
+        assert!(!poll_once_and_drop(a.set_code(0xAAAA)));
+        assert_eq!(b.code().await.unwrap(), 18);
+        assert_eq!(a.code().await.unwrap(), 0xAAAA);
+    });
+    let model = shared.into_inner().destroy().model;
+    assert_eq!(model.output(0), Some(0xAAAA));
+    assert_eq!(model.output(3), Some(27));
+    assert_eq!(model.frames(), 32);
+}
@@ -0,0 +1,75 @@
+use ad57xx::ad57x2::ChannelDual;
+use ad57xx::ad57x4::ChannelQuad;
+use ad57xx::channels::ChannelSet;
+use ad57xx::marker;
+use ad57xx::{Ad57xx, Ad57xxShared, OutputRange};
+
+mod common;
+use common::Recorder;
+
+#[test]
+fn set_operations() {
+    let set = ChannelQuad::DacA | ChannelQuad::DacC;
+    assert_eq!(set.bits(), 0b0101);
+    assert_eq!(set.len(), 2);
+    assert!(set.contains(ChannelQuad::DacC));
+    assert!(!set.contains(ChannelQuad::AllDacs));
+    assert_eq!(
+        set.iter().collect::<Vec<_>>(),
+        [ChannelQuad::DacA, ChannelQuad::DacC]
+    );
+    assert!((set | ChannelQuad::DacB | ChannelQuad::DacD).is_all());
+    assert_eq!(
+        ChannelSet::from(ChannelQuad::AllDacs).without(ChannelQuad::DacB),
+        ChannelSet::from_channels(&[ChannelQuad::DacA, ChannelQuad::DacC, ChannelQuad::DacD])
+    );
+    assert!(ChannelSet::<marker::Ad57x4>::empty().is_empty());
+
+    // Channel B of a dual part is at address 2
+    let dual = ChannelDual::DacA | ChannelDual::DacB;
+    assert_eq!(dual, ChannelSet::all());
+    assert_eq!(dual.bits(), 0b0101);
+}
+
+#[test]
+fn minimal_frames() {
+    let mut dac = Ad57xxShared::new_ad57x4(Recorder::default());
+    dac.set_dac_output(ChannelQuad::DacA | ChannelQuad::DacC, 0x1234)
+        .unwrap();
+    dac.set_output_range(ChannelSet::all(), OutputRange::Bipolar5V)
+        .unwrap();
+    dac.set_dac_output(ChannelSet::empty(), 0x1234).unwrap();
+    dac.set_power(ChannelQuad::DacB | ChannelQuad::DacD, true)
+        .unwrap();
+    dac.set_power(ChannelQuad::DacD, false).unwrap();
+    assert_eq!(
+        dac.destroy().frames,
+        [
+            [0x00, 0x12, 0x34],
+            [0x02, 0x12, 0x34],
+            [0x0C, 0x00, 0x03],
+            [0x10, 0x00, 0x0A],
+            [0x10, 0x00, 0x02],
+        ]
+    );
+
+    let mut dac = Ad57xxShared::new_ad57x2(Recorder::default());
+    dac.set_output_range(
+        ChannelDual::DacA | ChannelDual::DacB,
+        OutputRange::Unipolar10V,
+    )
+    .unwrap();
+    dac.set_dac_output(ChannelDual::DacB, 0xFFFF).unwrap();
+    assert!(dac
+        .set_output_range(ChannelDual::DacA, OutputRange::InvalidReadback)
+        .is_err());
+    // The trait method rejects it as well
+    assert!(
+        Ad57xx::set_output_range(&mut dac, ChannelDual::DacA, OutputRange::InvalidReadback)
+            .is_err()
+    );
+    assert_eq!(
+        dac.destroy().frames,
+        [[0x0C, 0x00, 0x01], [0x02, 0xFF, 0xFF]]
+    );
+}
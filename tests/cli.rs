@@ -0,0 +1,75 @@
+#![cfg(feature = "std")]
+use std::process::{Command, Output};
+
+fn ad57xx(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_ad57xx"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn dump_after_writes() {
+    let out = ad57xx(&[
+        "--model",
+        "power",
+        "all",
+        "on",
+        ",",
+        "range",
+        "b",
+        "bi10",
+        ",",
+        "set",
+        "b",
+        "-2.5V",
+        ",",
+        "set",
+        "a",
+        "0x1234",
+        ",",
+        "config",
+        "clamp_enable",
+        ",",
+        "dump",
+    ]);
+    assert!(out.status.success());
+    assert_eq!(
+        String::from_utf8(out.stdout).unwrap(),
+        "dac a   0x1234  0V to +5V\n\
+         dac b   0x6000  -10V to +10V\n\
+         dac c   0x0000  0V to +5V\n\
+         dac d   0x0000  0V to +5V\n\
+         power   0x000F  up: [a b c d]\n\
+         config  0x04    [clamp_enable]\n"
+    );
+}
+
+#[test]
+fn decode_frames() {
+    let out = ad57xx(&["--dual", "decode", "0x028000", ",", "decode", "0x190006"]);
+    assert!(out.status.success());
+    assert_eq!(
+        String::from_utf8(out.stdout).unwrap(),
+        "write dac register, dac b: 0x8000\nwrite configuration: [clr_select clamp_enable]\n"
+    );
+}
+
+#[test]
+fn help() {
+    let out = ad57xx(&["--help"]);
+    assert!(out.status.success());
+    assert!(out.stderr.is_empty());
+    assert!(String::from_utf8(out.stdout).unwrap().starts_with("Usage"));
+}
+
+#[test]
+fn errors() {
+    let out = ad57xx(&["--model", "set", "e", "3"]);
+    assert_eq!(out.status.code(), Some(2));
+    let out = ad57xx(&["--model", "set", "a", "6V"]);
+    assert_eq!(out.status.code(), Some(1));
+    assert!(String::from_utf8(out.stderr)
+        .unwrap()
+        .contains("outside the range"));
+}
@@ -0,0 +1,79 @@
+use ad57xx::ad57x4::ChannelQuad;
+use ad57xx::coding::Coding;
+use ad57xx::range::RangeRoute;
+use ad57xx::{Ad57xxShared, Error, OutputRange};
+use embedded_hal_mock::eh1::spi::Mock as MockSpi;
+
+mod common;
+use common::write;
+
+#[test]
+fn encode_decode() {
+    assert_eq!(Coding::OffsetBinary.encode(i16::MIN), 0x0000);
+    assert_eq!(Coding::OffsetBinary.encode(0), 0x8000);
+    assert_eq!(Coding::OffsetBinary.encode(i16::MAX), 0xFFFF);
+    assert_eq!(Coding::TwosComplement.encode(-0x4000), 0xC000);
+    // 12 bit value, left aligned
+    assert_eq!(Coding::OffsetBinary.encode(-1 << 4), 0x7FF0);
+    for val in [i16::MIN, -1, 0, 1, i16::MAX] {
+        assert_eq!(Coding::OffsetBinary.decode(Coding::OffsetBinary.encode(val)), val);
+        assert_eq!(Coding::TwosComplement.decode(Coding::TwosComplement.encode(val)), val);
+    }
+}
+
+#[test]
+fn set_dac_signed_twos_complement() {
+    let mut trans = vec![];
+    trans.extend(write([0b00001000, 0x00, 0b011]));
+    trans.extend(write([0b00000000, 0xC0, 0x00]));
+    let spi = MockSpi::new(&trans);
+
+    let mut dac = Ad57xxShared::new_ad57x4(spi);
+    dac.set_coding(Coding::TwosComplement);
+    dac.set_output_range(ChannelQuad::DacA, OutputRange::Bipolar5V)
+        .unwrap();
+    dac.set_dac_signed(ChannelQuad::DacA, -0x4000).unwrap();
+    // Channel B is still in the unipolar reset range
+    assert!(matches!(
+        dac.set_dac_signed(ChannelQuad::DacB, 0),
+        Err(Error::InvalidArgument)
+    ));
+    dac.destroy().done();
+}
+
+#[test]
+fn range_change_respects_coding() {
+    let mut trans = vec![];
+    trans.extend(write([0b00001000, 0x00, 0b011]));
+    trans.extend(write([0b00000000, 0x40, 0x00]));
+    // +2.5V in the +-5V range is 0x4000 in two's complement and 0x8000 in the
+    // 0V to 5V range
+    trans.extend(write([0b00001000, 0x00, 0b000]));
+    trans.extend(write([0b00000000, 0x80, 0x00]));
+    trans.extend(write([0b00011101, 0x00, 0x00]));
+    let spi = MockSpi::new(&trans);
+
+    let mut dac = Ad57xxShared::new_ad57x4(spi);
+    dac.set_coding(Coding::TwosComplement);
+    dac.set_output_range(ChannelQuad::DacA, OutputRange::Bipolar5V)
+        .unwrap();
+    dac.set_dac_signed(ChannelQuad::DacA, 0x4000).unwrap();
+    dac.change_range_safely(ChannelQuad::DacA, OutputRange::Unipolar5V, RangeRoute::Direct)
+        .unwrap();
+    dac.destroy().done();
+}
+
+#[cfg(feature = "readback")]
+#[test]
+fn get_dac_signed_decodes_readback() {
+    let mut trans = vec![];
+    trans.extend(write([0b00001011, 0x00, 0b100]));
+    trans.extend(common::read(0b10000011, 0x4000));
+    let spi = MockSpi::new(&trans);
+
+    let mut dac = Ad57xxShared::new_ad57x4(spi);
+    dac.set_output_range(ChannelQuad::DacD, OutputRange::Bipolar10V)
+        .unwrap();
+    assert_eq!(dac.get_dac_signed(ChannelQuad::DacD).unwrap(), -0x4000);
+    dac.destroy().done();
+}
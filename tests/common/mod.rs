@@ -0,0 +1,88 @@
+//! Mock transaction helpers shared by the integration tests
+#![allow(dead_code)]
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use ad57xx::marker::Ad57x4;
+use ad57xx::model::Model;
+use embedded_hal_mock::eh1::spi::Transaction as MockTransaction;
+
+pub mod trace;
+
+/// A single 24bit write frame
+pub fn write(payload: [u8; 3]) -> [MockTransaction<u8>; 3] {
+    [
+        MockTransaction::transaction_start(),
+        MockTransaction::write_vec(payload.to_vec()),
+        MockTransaction::transaction_end(),
+    ]
+}
+
+/// A readback of the register addressed by `cmd`, answering with `value`
+/// shifted out as `[cmd, MSB, LSB]`
+pub fn read(cmd: u8, value: u16) -> [MockTransaction<u8>; 6] {
+    [
+        MockTransaction::transaction_start(),
+        MockTransaction::write_vec(vec![cmd, 0x00, 0x00]),
+        MockTransaction::transaction_end(),
+        MockTransaction::transaction_start(),
+        MockTransaction::transfer(
+            vec![0x18, 0x00, 0x00],
+            vec![cmd, (value >> 8) as u8, value as u8],
+        ),
+        MockTransaction::transaction_end(),
+    ]
+}
+
+/// SPI device recording every written frame, failing all transactions while
+/// `fail` is set
+#[derive(Debug, Default)]
+pub struct Recorder {
+    pub frames: Vec<Vec<u8>>,
+    pub fail: bool,
+}
+
+impl embedded_hal::spi::ErrorType for Recorder {
+    type Error = embedded_hal::spi::ErrorKind;
+}
+
+impl embedded_hal::spi::SpiDevice for Recorder {
+    fn transaction(
+        &mut self,
+        operations: &mut [embedded_hal::spi::Operation<'_, u8>],
+    ) -> Result<(), Self::Error> {
+        if self.fail {
+            return Err(embedded_hal::spi::ErrorKind::Other);
+        }
+        for op in operations {
+            if let embedded_hal::spi::Operation::Write(buf) = op {
+                self.frames.push(buf.to_vec());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Quad channel model that outlives the driver and can be inspected or reset
+/// behind its back
+#[derive(Clone)]
+pub struct Shared(pub Rc<RefCell<Model<Ad57x4>>>);
+
+impl Default for Shared {
+    fn default() -> Self {
+        Shared(Rc::new(RefCell::new(Model::ad57x4())))
+    }
+}
+
+impl embedded_hal::spi::ErrorType for Shared {
+    type Error = core::convert::Infallible;
+}
+
+impl embedded_hal::spi::SpiDevice for Shared {
+    fn transaction(
+        &mut self,
+        operations: &mut [embedded_hal::spi::Operation<'_, u8>],
+    ) -> Result<(), Self::Error> {
+        self.0.borrow_mut().transaction(operations)
+    }
+}
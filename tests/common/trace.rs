@@ -0,0 +1,138 @@
+//! Trace recording SPI device and golden file comparison
+use std::cell::RefCell;
+use std::fmt::Write as _;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use ad57xx::decode::DecodedFrame;
+use embedded_hal::spi::{ErrorType, Operation, SpiDevice};
+
+/// Handle to the log of a [`Trace`], shared with the traced device
+#[derive(Debug, Clone, Default)]
+pub struct Log(Rc<RefCell<String>>);
+
+impl Log {
+    /// Start a new section of the trace
+    pub fn section(&self, title: &str) {
+        let mut log = self.0.borrow_mut();
+        if !log.is_empty() {
+            log.push('\n');
+        }
+        writeln!(log, "# {title}").unwrap();
+    }
+
+    /// The trace recorded so far
+    pub fn text(&self) -> String {
+        self.0.borrow().clone()
+    }
+
+    fn line(&self, line: &str) {
+        writeln!(self.0.borrow_mut(), "{}", line.trim_end()).unwrap();
+    }
+}
+
+/// SPI device wrapper logging every transaction as decoded frames
+pub struct Trace<DEV> {
+    pub spi: DEV,
+    /// Decoder of the traced part
+    decode: fn([u8; 3]) -> DecodedFrame,
+    log: Log,
+}
+
+impl<DEV> Trace<DEV> {
+    /// Trace a quad channel device
+    pub fn quad(spi: DEV) -> Self {
+        Self::new(spi, DecodedFrame::quad)
+    }
+
+    /// Trace a dual channel device
+    pub fn dual(spi: DEV) -> Self {
+        Self::new(spi, DecodedFrame::dual)
+    }
+
+    fn new(spi: DEV, decode: fn([u8; 3]) -> DecodedFrame) -> Self {
+        Trace {
+            spi,
+            decode,
+            log: Log::default(),
+        }
+    }
+
+    /// Handle to the log
+    pub fn log(&self) -> Log {
+        self.log.clone()
+    }
+
+    /// Human readable description of a frame
+    fn describe(&self, frame: &[u8]) -> String {
+        match <[u8; 3]>::try_from(frame) {
+            Ok(frame) => (self.decode)(frame).to_string(),
+            Err(_) => format!("{} byte frame", frame.len()),
+        }
+    }
+
+    fn record(&self, dir: &str, bytes: &[u8], note: &str) {
+        let hex: Vec<_> = bytes.iter().map(|b| format!("{b:02X}")).collect();
+        self.log.line(&format!("{dir} {}  {note}", hex.join(" ")));
+    }
+}
+
+impl<DEV: ErrorType> ErrorType for Trace<DEV> {
+    type Error = DEV::Error;
+}
+
+impl<DEV: SpiDevice> SpiDevice for Trace<DEV> {
+    fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), Self::Error> {
+        self.spi.transaction(operations)?;
+        for op in operations.iter() {
+            match op {
+                Operation::Write(buf) => self.record(">", buf, &self.describe(buf)),
+                Operation::Transfer(rx, tx) => {
+                    self.record(">", tx, &self.describe(tx));
+                    self.record("<", rx, "");
+                }
+                Operation::TransferInPlace(buf) => self.record("<>", buf, ""),
+                Operation::Read(buf) => self.record("<", buf, ""),
+                Operation::DelayNs(ns) => self.log.line(&format!("delay {ns}ns")),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Compare a trace with `tests/golden/<name>.trace`. The golden file is
+/// (re)written instead when `UPDATE_GOLDEN` is set.
+pub fn assert_golden(name: &str, trace: &str) {
+    let path: PathBuf = [
+        env!("CARGO_MANIFEST_DIR"),
+        "tests",
+        "golden",
+        &format!("{name}.trace"),
+    ]
+    .iter()
+    .collect();
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, trace).unwrap();
+        return;
+    }
+    let golden = std::fs::read_to_string(&path).unwrap_or_else(|e| {
+        panic!(
+            "cannot read {}: {e}, run with UPDATE_GOLDEN=1",
+            path.display()
+        )
+    });
+    if golden != trace {
+        let mut diff = String::new();
+        for (i, (want, got)) in golden.lines().zip(trace.lines()).enumerate() {
+            if want != got {
+                writeln!(diff, "line {}:\n  - {want}\n  + {got}", i + 1).unwrap();
+            }
+        }
+        let (want, got) = (golden.lines().count(), trace.lines().count());
+        if want != got {
+            writeln!(diff, "expected {want} lines, got {got}").unwrap();
+        }
+        panic!("trace differs from {}:\n{diff}", path.display());
+    }
+}
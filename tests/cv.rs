@@ -0,0 +1,84 @@
+use ad57xx::ad57x4::ChannelQuad;
+use ad57xx::cv::{Calibration, GateChannel, PitchChannel, Scale};
+use ad57xx::{Ad57xxShared, OutputRange};
+use embedded_hal_mock::eh1::spi::Mock as MockSpi;
+
+mod common;
+use common::write;
+
+#[test]
+fn quantize() {
+    assert_eq!(Scale::Chromatic.quantize(1), 1);
+    // C# snaps down to C, A# down to A
+    assert_eq!(Scale::Major.quantize(1), 0);
+    assert_eq!(Scale::Major.quantize(10), 9);
+    assert_eq!(Scale::Minor.quantize(4), 3);
+    assert_eq!(Scale::Minor.quantize(-1), -2);
+    // Pentatonic C D E G A
+    let penta = Scale::Custom(0b0010_1001_0101);
+    assert_eq!(penta.quantize(5), 4);
+    assert_eq!(penta.quantize(6), 7);
+    assert_eq!(penta.quantize(11), 12);
+    assert_eq!(Scale::Custom(0).quantize(5), 5);
+}
+
+#[test]
+fn calibrated_note_voltage() {
+    let cal = Calibration::default();
+    assert_eq!(cal.note_to_microvolts(12), 1_000_000);
+    assert_eq!(cal.note_to_microvolts(-24), -2_000_000);
+    assert_eq!(cal.note_to_microvolts(1), 83_333);
+    let cal = Calibration {
+        scale_ppm: 1_000,
+        offset_uv: -500,
+    };
+    assert_eq!(cal.note_to_microvolts(12), 1_000_500);
+}
+
+#[test]
+fn pitch_glide() {
+    let mut trans = vec![];
+    trans.extend(write([0b00001100, 0x00, 0b100]));
+    // 0V, then gliding up to 1V in 0.5V steps
+    trans.extend(write([0b00000000, 0x80, 0x00]));
+    trans.extend(write([0b00000000, 0x86, 0x66]));
+    trans.extend(write([0b00000000, 0x8C, 0xCD]));
+    let spi = MockSpi::new(&trans);
+
+    let mut dac = Ad57xxShared::new_ad57x4(spi);
+    dac.set_output_range(ChannelQuad::AllDacs, OutputRange::Bipolar10V)
+        .unwrap();
+    let mut pitch = PitchChannel::new(ChannelQuad::DacA)
+        .with_scale(Scale::Major)
+        .with_glide(500_000);
+    pitch.tick(&mut dac).unwrap();
+    // Transposed by an octave after quantising C# to C
+    pitch.set_transpose(12);
+    pitch.note_on(1);
+    assert_eq!(pitch.target_microvolts(), 1_000_000);
+    pitch.tick(&mut dac).unwrap();
+    assert!(pitch.is_gliding());
+    pitch.tick(&mut dac).unwrap();
+    assert!(!pitch.is_gliding());
+    pitch.tick(&mut dac).unwrap();
+    dac.destroy().done();
+}
+
+#[test]
+fn gate_trigger() {
+    let mut trans = vec![];
+    trans.extend(write([0b00001001, 0x00, 0b001]));
+    trans.extend(write([0b00000001, 0x80, 0x00]));
+    trans.extend(write([0b00000001, 0x00, 0x00]));
+    let spi = MockSpi::new(&trans);
+
+    let mut dac = Ad57xxShared::new_ad57x4(spi);
+    dac.set_output_range(ChannelQuad::DacB, OutputRange::Unipolar10V)
+        .unwrap();
+    let mut gate = GateChannel::new(ChannelQuad::DacB).with_trigger_length(2);
+    gate.trigger(&mut dac).unwrap();
+    gate.tick(&mut dac).unwrap();
+    gate.tick(&mut dac).unwrap();
+    gate.tick(&mut dac).unwrap();
+    dac.destroy().done();
+}
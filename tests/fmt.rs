@@ -0,0 +1,40 @@
+use ad57xx::decode::{DecodedConfig, DecodedFrame, DecodedPower};
+use ad57xx::{Error, OutputRange};
+use embedded_hal::spi::{Error as _, ErrorKind};
+
+#[test]
+fn error_display_and_kind() {
+    let err: Error<ErrorKind> = Error::Spi(ErrorKind::ModeFault);
+    assert_eq!(err.to_string(), "SPI communication error: ModeFault");
+    assert_eq!(err.kind(), ErrorKind::ModeFault);
+
+    let err: Error<ErrorKind> = Error::InvalidArgument;
+    assert_eq!(err.to_string(), "invalid argument");
+    assert_eq!(err.kind(), ErrorKind::Other);
+
+    let err: Box<dyn std::error::Error> = Box::new(Error::<ErrorKind>::ReadError);
+    assert_eq!(err.to_string(), "invalid readback data");
+}
+
+#[test]
+fn output_range_display() {
+    assert_eq!(OutputRange::Unipolar10_8V.to_string(), "0V to +10.8V");
+    assert_eq!(OutputRange::Bipolar5V.to_string(), "-5V to +5V");
+}
+
+#[test]
+fn decoded_frames() {
+    assert_eq!(
+        DecodedFrame::quad([0x0B, 0x00, 0x05]).to_string(),
+        "write range select register, dac d: -10.8V to +10.8V"
+    );
+    assert_eq!(
+        DecodedFrame::dual([0x81, 0x00, 0x00]).to_string(),
+        "read dac register, invalid address 1"
+    );
+    assert_eq!(
+        DecodedPower::dual(0b10_0010_0101).to_string(),
+        "up: [a b] thermal shutdown overcurrent: [b]"
+    );
+    assert_eq!(DecodedConfig(0b1001).to_string(), "[sdo_disable tsd_enable]");
+}
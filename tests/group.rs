@@ -0,0 +1,78 @@
+use ad57xx::group::{GroupError, LdacGroup, Staged};
+use ad57xx::mapper::Chip;
+use ad57xx::{Ad57xxShared, Error};
+use embedded_hal_mock::eh1::pin::{Mock as PinMock, State, Transaction as PinTransaction};
+
+mod common;
+use common::Recorder;
+
+fn frames(chip: Chip<Recorder>) -> Vec<Vec<u8>> {
+    match chip {
+        Chip::Quad(dac) => dac.destroy().frames,
+        Chip::Dual(dac) => dac.destroy().frames,
+    }
+}
+
+#[test]
+fn stage_then_commit() {
+    let ldac = PinMock::new(&[
+        PinTransaction::set(State::High),
+        PinTransaction::set(State::Low),
+        PinTransaction::set(State::High),
+    ]);
+    let mut group = LdacGroup::new(
+        [
+            Chip::from(Ad57xxShared::new_ad57x4(Recorder::default())),
+            Chip::from(Ad57xxShared::new_ad57x2(Recorder::default())),
+        ],
+        ldac,
+    )
+    .unwrap();
+    group
+        .update(&[Staged::new(0, 3, 0x8000), Staged::new(1, 1, 0x4000)])
+        .unwrap();
+    let ([quad, dual], mut ldac) = group.destroy();
+    assert_eq!(frames(quad), [vec![0b00000011, 0x80, 0x00]]);
+    assert_eq!(frames(dual), [vec![0b00000010, 0x40, 0x00]]);
+    ldac.done();
+}
+
+#[test]
+fn staging_failure_is_not_committed() {
+    let ldac = PinMock::new(&[PinTransaction::set(State::High)]);
+    let failing = Recorder {
+        fail: true,
+        ..Default::default()
+    };
+    let mut group = LdacGroup::new(
+        [
+            Chip::from(Ad57xxShared::new_ad57x4(Recorder::default())),
+            Chip::from(Ad57xxShared::new_ad57x4(failing)),
+            Chip::from(Ad57xxShared::new_ad57x2(Recorder::default())),
+        ],
+        ldac,
+    )
+    .unwrap();
+    let result = group.update(&[
+        Staged::new(0, 0, 0x1000),
+        Staged::new(1, 0, 0x2000),
+        Staged::new(1, 1, 0x3000),
+        Staged::new(2, 2, 0x4000),
+    ]);
+    match result {
+        Err(GroupError::Staging {
+            failed,
+            chip,
+            error,
+        }) => {
+            // Device 2 has no third channel
+            assert_eq!(failed, 0b110);
+            assert_eq!(chip, 1);
+            assert!(matches!(error, Error::Spi(_)));
+        }
+        _ => panic!("staging should fail"),
+    }
+    let ([first, _, _], mut ldac) = group.destroy();
+    assert_eq!(frames(first), [vec![0b00000000, 0x10, 0x00]]);
+    ldac.done();
+}
@@ -1,4 +1,6 @@
-use ad57xx::{Ad57xx, Ad57xxShared};
+#[cfg(feature = "readback")]
+use ad57xx::Ad57xx;
+use ad57xx::Ad57xxShared;
 use embedded_hal_mock::eh1::spi::{Mock as MockSpi, Transaction as MockTransaction};
 
 #[test]
@@ -27,6 +29,7 @@ fn write_dac_b() {
     dac.set_dac_output(ad57xx::ad57x2::ChannelDual::DacB, 0xF00F).unwrap();
     dac.destroy().done();
 }
+#[cfg(feature = "readback")]
 #[test]
 fn read_config() {
     let trans = [
@@ -34,7 +37,7 @@ fn read_config() {
         MockTransaction::write_vec(vec![0b10011001, 0x00, 0x00]),
         MockTransaction::transaction_end(),
         MockTransaction::transaction_start(),
-        MockTransaction::transfer(vec![0b00011000, 0x00, 0x00], vec![0x04, 0x00, 0x00]),
+        MockTransaction::transfer(vec![0b00011000, 0x00, 0x00], vec![0b10011001, 0x00, 0x04]),
         MockTransaction::transaction_end(),
     ];
     let spi = MockSpi::new(&trans);
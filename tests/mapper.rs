@@ -0,0 +1,95 @@
+use ad57xx::ad57x2::ChannelDual;
+use ad57xx::ad57x4::ChannelQuad;
+use ad57xx::mapper::{ChannelInfo, ChannelMap, Chip, Location};
+use ad57xx::{Ad57xxShared, Error, OutputRange};
+use embedded_hal_mock::eh1::spi::Mock as MockSpi;
+
+mod common;
+use common::write;
+
+fn info() -> [ChannelInfo; 6] {
+    let mut info = [ChannelInfo::new("cv", OutputRange::Bipolar10V); 6];
+    info[5] = ChannelInfo::new("gate", OutputRange::Unipolar5V).with_limits(0, 5_000_000);
+    info
+}
+
+#[test]
+fn locate_channels() {
+    let quad = MockSpi::new(&[]);
+    let dual = MockSpi::new(&[]);
+    let map = ChannelMap::new(
+        [
+            Chip::from(Ad57xxShared::new_ad57x4(quad)),
+            Chip::from(Ad57xxShared::new_ad57x2(dual)),
+        ],
+        info(),
+    )
+    .ok()
+    .unwrap();
+    assert_eq!(map.channel_count(), 6);
+    assert_eq!(map.locate(0), Some(Location::Quad(0, ChannelQuad::DacA)));
+    assert_eq!(map.locate(3), Some(Location::Quad(0, ChannelQuad::DacD)));
+    assert_eq!(map.locate(5), Some(Location::Dual(1, ChannelDual::DacB)));
+    assert_eq!(map.locate(6), None);
+    assert_eq!(map.info(5).unwrap().name, "gate");
+    for chip in map.destroy() {
+        match chip {
+            Chip::Quad(dac) => dac.destroy().done(),
+            Chip::Dual(dac) => dac.destroy().done(),
+        }
+    }
+}
+
+#[test]
+fn channel_count_mismatch() {
+    let quad = MockSpi::new(&[]);
+    let map = ChannelMap::new([Chip::from(Ad57xxShared::new_ad57x4(quad))], info());
+    let [chip] = map.err().unwrap();
+    if let Chip::Quad(dac) = chip {
+        dac.destroy().done();
+    }
+}
+
+#[test]
+fn fan_out_and_limits() {
+    let mut quad_trans = vec![];
+    quad_trans.extend(write([0b00001000, 0x00, 0b100]));
+    quad_trans.extend(write([0b00001001, 0x00, 0b100]));
+    quad_trans.extend(write([0b00001010, 0x00, 0b100]));
+    quad_trans.extend(write([0b00001011, 0x00, 0b100]));
+    quad_trans.extend(write([0b00010000, 0x00, 0b1111]));
+    quad_trans.extend(write([0b00011101, 0x00, 0x00]));
+    let mut dual_trans = vec![];
+    dual_trans.extend(write([0b00001000, 0x00, 0b100]));
+    dual_trans.extend(write([0b00001010, 0x00, 0b000]));
+    dual_trans.extend(write([0b00010000, 0x00, 0b0101]));
+    dual_trans.extend(write([0b00000010, 0x80, 0x00]));
+    dual_trans.extend(write([0b00011101, 0x00, 0x00]));
+    let quad = MockSpi::new(&quad_trans);
+    let dual = MockSpi::new(&dual_trans);
+
+    let mut map = ChannelMap::new(
+        [
+            Chip::from(Ad57xxShared::new_ad57x4(quad)),
+            Chip::from(Ad57xxShared::new_ad57x2(dual)),
+        ],
+        info(),
+    )
+    .ok()
+    .unwrap();
+    map.apply_ranges().unwrap();
+    map.set_power_all(true).unwrap();
+    map.set_microvolts(5, 2_500_000).unwrap();
+    assert!(matches!(
+        map.set_microvolts(5, 5_000_001),
+        Err(Error::InvalidArgument)
+    ));
+    assert!(matches!(map.set_code(6, 0), Err(Error::InvalidArgument)));
+    map.load_all().unwrap();
+    for chip in map.destroy() {
+        match chip {
+            Chip::Quad(dac) => dac.destroy().done(),
+            Chip::Dual(dac) => dac.destroy().done(),
+        }
+    }
+}
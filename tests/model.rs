@@ -0,0 +1,70 @@
+use ad57xx::ad57x2::ChannelDual;
+use ad57xx::ad57x4::ChannelQuad;
+use ad57xx::model::Model;
+#[cfg(feature = "readback")]
+use ad57xx::Config;
+use ad57xx::{Ad57xx, Ad57xxShared, OutputRange};
+
+#[test]
+fn writes_update_registers_and_outputs() {
+    let mut dac = Ad57xxShared::new_ad57x4(Model::ad57x4());
+    dac.set_output_range(ChannelQuad::AllDacs, OutputRange::Bipolar10V)
+        .unwrap();
+    dac.set_power(ChannelQuad::DacB, true).unwrap();
+    dac.set_dac_output(ChannelQuad::DacB, 0x1234).unwrap();
+    dac.set_dac_output(ChannelQuad::DacC, 0x4321).unwrap();
+    let model = dac.destroy();
+    assert_eq!(model.frames(), 4);
+    assert_eq!(model.range(3), Some(OutputRange::Bipolar10V));
+    assert_eq!(model.output(1), Some(0x1234));
+    // Channel C is powered down
+    assert_eq!(model.dac_register(2), Some(0x4321));
+    assert_eq!(model.output(2), None);
+}
+
+#[test]
+fn ldac_and_clear() {
+    let mut model = Model::ad57x2();
+    model.set_ldac(true);
+    let mut dac = Ad57xxShared::new_ad57x2(model);
+    dac.set_power(ChannelDual::AllDacs, true).unwrap();
+    dac.set_output_range(ChannelDual::DacB, OutputRange::Bipolar5V)
+        .unwrap();
+    dac.set_dac_output(ChannelDual::AllDacs, 0xFFFF).unwrap();
+    let mut model = dac.destroy();
+    assert_eq!(model.output(0), Some(0x0000));
+    assert_eq!(model.dac_register(1), None);
+
+    let mut dac = Ad57xxShared::new_ad57x2(model);
+    dac.load_dacs().unwrap();
+    model = dac.destroy();
+    assert_eq!(model.output(2), Some(0xFFFF));
+
+    let mut dac = Ad57xxShared::new_ad57x2(model);
+    dac.clear_dacs().unwrap();
+    model = dac.destroy();
+    assert_eq!(model.output(0), Some(0x0000));
+    assert_eq!(model.output(2), Some(0x8000));
+}
+
+#[cfg(feature = "readback")]
+#[test]
+fn readback() {
+    use ad57xx::{Command, Data};
+
+    let mut dac = Ad57xxShared::new_ad57x4(Model::ad57x4());
+    dac.set_dac_output(ChannelQuad::DacD, 0xBEEF).unwrap();
+    let cfg = Config::new().with_clr_select(true);
+    dac.set_config(cfg).unwrap();
+    assert_eq!(dac.get_config().unwrap(), cfg);
+    match dac.read(Command::DacRegister(ChannelQuad::DacD)).unwrap() {
+        Data::DacValue(code) => assert_eq!(code, 0xBEEF),
+        data => panic!("unexpected {data:?}"),
+    }
+    // No data is shifted out with SDO disabled
+    dac.set_config(cfg.with_sdo_disable(true)).unwrap();
+    match dac.read(Command::DacRegister(ChannelQuad::DacD)).unwrap() {
+        Data::DacValue(code) => assert_eq!(code, 0x0000),
+        data => panic!("unexpected {data:?}"),
+    }
+}
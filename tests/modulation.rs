@@ -0,0 +1,101 @@
+use ad57xx::ad57x4::ChannelQuad;
+use ad57xx::coding::Coding;
+use ad57xx::modulation::{Adsr, Bank, Envelope, Lfo, Shape, Source, Stage};
+use ad57xx::{Ad57xxShared, Error, OutputRange};
+use embedded_hal_mock::eh1::spi::Mock as MockSpi;
+
+mod common;
+use common::write;
+
+#[test]
+fn adsr_stages() {
+    let mut env = Envelope::new(Adsr::new(2, 2, 0x8000, 4));
+    assert_eq!(env.tick(), 0);
+    env.gate(true);
+    assert_eq!(env.tick(), 0x7FFF);
+    assert_eq!(env.tick(), 0xFFFF);
+    assert_eq!(env.stage(), Stage::Decay);
+    assert_eq!(env.tick(), 0xBFFF);
+    assert_eq!(env.tick(), 0x8000);
+    assert_eq!(env.tick(), 0x8000);
+    assert_eq!(env.stage(), Stage::Sustain);
+    env.gate(false);
+    assert_eq!(env.tick(), 0x4000);
+    assert_eq!(env.tick(), 0x0000);
+    assert_eq!(env.stage(), Stage::Release);
+    assert_eq!(env.tick(), 0x0000);
+    assert_eq!(env.stage(), Stage::Idle);
+}
+
+#[test]
+fn lfo_shapes() {
+    let mut square = Lfo::new(Shape::Square, 4);
+    let levels: Vec<u16> = (0..4).map(|_| square.tick()).collect();
+    assert_eq!(levels, [0xFFFF, 0xFFFF, 0x0000, 0x0000]);
+
+    let mut saw = Lfo::new(Shape::SawUp, 4).with_depth(0x7FFF);
+    let levels: Vec<u16> = (0..4).map(|_| saw.tick()).collect();
+    assert_eq!(levels, [0x4000, 0x6000, 0x8000, 0xA000]);
+
+    let mut sine = Lfo::new(Shape::Sine, 4);
+    let levels: Vec<u16> = (0..4).map(|_| sine.tick()).collect();
+    assert_eq!(levels, [0x8000, 0xFFFF, 0x8000, 0x0001]);
+    sine.tick();
+    sine.sync();
+    assert_eq!(sine.tick(), 0x8000);
+}
+
+#[test]
+fn batched_bank_loads_once() {
+    let mut trans = vec![];
+    trans.extend(write([0b00001001, 0x00, 0b011]));
+    // Envelope on DAC A, square LFO in two's complement on DAC B
+    trans.extend(write([0b00000000, 0xFF, 0xFF]));
+    trans.extend(write([0b00000001, 0x7F, 0xFF]));
+    trans.extend(write([0b00011101, 0x00, 0x00]));
+    // Only the LFO changes on the second tick
+    trans.extend(write([0b00000001, 0x80, 0x00]));
+    trans.extend(write([0b00011101, 0x00, 0x00]));
+    let spi = MockSpi::new(&trans);
+
+    let mut dac = Ad57xxShared::new_ad57x4(spi);
+    dac.set_coding(Coding::TwosComplement);
+    dac.set_output_range(ChannelQuad::DacB, OutputRange::Bipolar5V)
+        .unwrap();
+    let mut bank = Bank::new([
+        (
+            ChannelQuad::DacA,
+            Source::Envelope(Envelope::new(Adsr::new(1, 1, 0xFFFF, 1))),
+        ),
+        (ChannelQuad::DacB, Source::Lfo(Lfo::new(Shape::Square, 2))),
+    ])
+    .with_batching(true);
+    bank.gate(0, true);
+    bank.tick(&mut dac).unwrap();
+    bank.tick(&mut dac).unwrap();
+    dac.destroy().done();
+}
+
+#[test]
+fn bank_rejects_all_dacs() {
+    let spi = MockSpi::new(&[]);
+    let mut dac = Ad57xxShared::new_ad57x4(spi);
+    let mut bank = Bank::new([(ChannelQuad::AllDacs, Source::Lfo(Lfo::new(Shape::Sine, 8)))]);
+    assert!(matches!(bank.tick(&mut dac), Err(Error::InvalidArgument)));
+
+    // Nothing is written or advanced when a later output is invalid
+    let mut bank = Bank::new([
+        (
+            ChannelQuad::DacA,
+            Source::Envelope(Envelope::new(Adsr::new(1, 1, 0xFFFF, 1))),
+        ),
+        (ChannelQuad::AllDacs, Source::Lfo(Lfo::new(Shape::Sine, 8))),
+    ]);
+    bank.gate(0, true);
+    assert!(matches!(bank.tick(&mut dac), Err(Error::InvalidArgument)));
+    let Some(Source::Envelope(env)) = bank.source_mut(0) else {
+        unreachable!()
+    };
+    assert_eq!(env.stage(), Stage::Attack);
+    dac.destroy().done();
+}
@@ -0,0 +1,205 @@
+use ad57xx::ad57x2::ChannelDual;
+use ad57xx::ad57x4::{ChannelQuad, PowerConfigQuad};
+use ad57xx::model::Model;
+use ad57xx::{Ad57xx, Ad57xxShared, Command, Config, Data, Error, Function, OutputRange};
+use proptest::prelude::*;
+
+mod common;
+use common::Recorder;
+
+/// Device answering every read with `value`, recording the written frames
+#[cfg(feature = "readback")]
+struct Readback {
+    value: u16,
+    writes: Vec<Vec<u8>>,
+}
+
+#[cfg(feature = "readback")]
+impl embedded_hal::spi::ErrorType for Readback {
+    type Error = core::convert::Infallible;
+}
+
+#[cfg(feature = "readback")]
+impl embedded_hal::spi::SpiDevice for Readback {
+    fn transaction(
+        &mut self,
+        operations: &mut [embedded_hal::spi::Operation<'_, u8>],
+    ) -> Result<(), Self::Error> {
+        use embedded_hal::spi::Operation;
+        for op in operations {
+            match op {
+                Operation::Write(buf) if buf[0] & 0x80 == 0 => self.writes.push(buf.to_vec()),
+                Operation::Transfer(rx, _) => {
+                    rx.copy_from_slice(&[0x00, (self.value >> 8) as u8, self.value as u8])
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Data written by the last frame
+#[cfg(feature = "readback")]
+fn last_data(writes: &[Vec<u8>]) -> u16 {
+    let frame = writes.last().unwrap();
+    (frame[1] as u16) << 8 | frame[2] as u16
+}
+
+fn quad() -> impl Strategy<Value = ChannelQuad> {
+    prop_oneof![
+        Just(ChannelQuad::DacA),
+        Just(ChannelQuad::DacB),
+        Just(ChannelQuad::DacC),
+        Just(ChannelQuad::DacD),
+        Just(ChannelQuad::AllDacs),
+    ]
+}
+
+fn dual() -> impl Strategy<Value = ChannelDual> {
+    prop_oneof![
+        Just(ChannelDual::DacA),
+        Just(ChannelDual::DacB),
+        Just(ChannelDual::AllDacs),
+    ]
+}
+
+fn range() -> impl Strategy<Value = OutputRange> {
+    (0u16..6).prop_map(OutputRange::from)
+}
+
+fn function() -> impl Strategy<Value = Function> {
+    prop_oneof![
+        Just(Function::Nop),
+        Just(Function::Config),
+        Just(Function::Clear),
+        Just(Function::Load),
+    ]
+}
+
+fn command() -> impl Strategy<Value = Command<ChannelQuad>> {
+    prop_oneof![
+        quad().prop_map(Command::DacRegister),
+        quad().prop_map(Command::RangeSelectRegister),
+        Just(Command::PowerControlRegister),
+        function().prop_map(Command::ControlRegister),
+    ]
+}
+
+/// Data of every kind, built from a kind and a raw value since `Data` is not
+/// `Clone`
+fn data(kind: u8, value: u16) -> Data<PowerConfigQuad> {
+    match kind {
+        0 => Data::DacValue(value),
+        1 => Data::OutputRange(OutputRange::from(value % 6)),
+        2 => Data::Control(Config::from(value as u8 & 0xF)),
+        3 => Data::PowerControl(value.into()),
+        _ => Data::None,
+    }
+}
+
+/// Whether `write` accepts the pair
+fn matches(cmd: &Command<ChannelQuad>, data: &Data<PowerConfigQuad>) -> bool {
+    matches!(
+        (cmd, data),
+        (Command::DacRegister(_), Data::DacValue(_))
+            | (Command::RangeSelectRegister(_), Data::OutputRange(_))
+            | (Command::PowerControlRegister, Data::PowerControl(_))
+            | (Command::ControlRegister(Function::Config), Data::Control(_))
+            | (Command::ControlRegister(Function::Nop), Data::None)
+            | (Command::ControlRegister(Function::Clear), Data::None)
+            | (Command::ControlRegister(Function::Load), Data::None)
+    )
+}
+
+proptest! {
+    #[test]
+    fn write_encodes_valid_frames(cmd in command(), kind in 0u8..5, value in any::<u16>()) {
+        let data = data(kind, value);
+        let mut dac = Ad57xxShared::new_ad57x4(Recorder::default());
+        let valid = matches(&cmd, &data);
+        let result = dac.write(cmd, data);
+        let frames = dac.destroy().frames;
+        if valid {
+            prop_assert!(result.is_ok());
+            prop_assert_eq!(frames.len(), 1);
+            let cmd_byte = frames[0][0];
+            // Write bit and the reserved zero bit are never set
+            prop_assert_eq!(cmd_byte & 0xC0, 0);
+            prop_assert_eq!((cmd_byte >> 3) & 0b111, u8::from(cmd));
+        } else {
+            prop_assert!(matches!(result, Err(Error::InvalidArgument)));
+            prop_assert!(frames.is_empty());
+        }
+    }
+
+    #[test]
+    fn quad_registers_round_trip(chan in quad(), code in any::<u16>(), range in range(), cfg in 0u8..16) {
+        let mut dac = Ad57xxShared::new_ad57x4(Model::ad57x4());
+        dac.set_dac_output(chan, code).unwrap();
+        dac.set_output_range(chan, range).unwrap();
+        // Readback needs SDO
+        let cfg = Config::from(cfg).with_sdo_disable(false);
+        dac.set_config(cfg).unwrap();
+        let single = if chan == ChannelQuad::AllDacs { ChannelQuad::DacD } else { chan };
+        prop_assert!(matches!(dac.read(Command::DacRegister(single)), Ok(Data::DacValue(c)) if c == code));
+        prop_assert!(matches!(dac.read(Command::RangeSelectRegister(single)), Ok(Data::OutputRange(r)) if r == range));
+        prop_assert_eq!(u8::from(dac.get_config().unwrap()), u8::from(cfg));
+    }
+
+    #[test]
+    fn dual_power_round_trip(chan in dual(), on in any::<bool>()) {
+        let mut dac = Ad57xxShared::new_ad57x2(Model::ad57x2());
+        dac.set_power(chan, on).unwrap();
+        let pcfg = u16::from(dac.get_power_config().unwrap());
+        let mask = match chan {
+            ChannelDual::AllDacs => 0b0101,
+            chan => 1 << u8::from(chan),
+        };
+        prop_assert_eq!(pcfg & mask, if on { mask } else { 0 });
+        // Channels B and D do not exist
+        prop_assert_eq!(pcfg & 0b1010, 0);
+    }
+
+    #[test]
+    fn decodes_any_readback(value in any::<u16>()) {
+        let range = OutputRange::from(value);
+        prop_assert_eq!(range == OutputRange::InvalidReadback, value > 0b101);
+        if range != OutputRange::InvalidReadback {
+            prop_assert_eq!(range as u16, value);
+        }
+        let cfg = Config::from(value as u8);
+        prop_assert_eq!(u8::from(cfg), value as u8);
+    }
+
+    #[cfg(feature = "readback")]
+    #[test]
+    fn power_readback_keeps_status_out_of_power_up(
+        value in any::<u16>(),
+        quad_chan in quad(),
+        dual_chan in dual(),
+        on in any::<bool>(),
+    ) {
+        // Thermal shutdown and overcurrent flags read back are never written
+        // as power-up bits
+        let mut dac = Ad57xxShared::new_ad57x4(Readback { value, writes: vec![] });
+        prop_assert_eq!(u16::from(dac.get_power_config().unwrap()), value);
+        dac.set_power(quad_chan, on).unwrap();
+        let mask = match u8::from(quad_chan) {
+            4 => 0xF,
+            addr => 1 << addr,
+        };
+        let expected = if on { value | mask } else { value & !mask } & 0xF;
+        prop_assert_eq!(last_data(&dac.destroy().writes), expected);
+
+        let mut dac = Ad57xxShared::new_ad57x2(Readback { value, writes: vec![] });
+        prop_assert_eq!(u16::from(dac.get_power_config().unwrap()), value);
+        dac.set_power(dual_chan, on).unwrap();
+        let mask = match u8::from(dual_chan) {
+            4 => 0b0101,
+            addr => 1 << addr,
+        };
+        let expected = if on { value | mask } else { value & !mask } & 0b0101;
+        prop_assert_eq!(last_data(&dac.destroy().writes), expected);
+    }
+}
@@ -0,0 +1,90 @@
+use ad57xx::ad57x2::ChannelDual;
+use ad57xx::ad57x4::ChannelQuad;
+use ad57xx::range::RangeRoute;
+use ad57xx::{Ad57xxShared, OutputRange};
+use embedded_hal_mock::eh1::spi::Mock as MockSpi;
+
+mod common;
+use common::write;
+
+#[test]
+fn direct_loads_code_before_widening_range() {
+    let mut trans = vec![];
+    trans.extend(write([0b00000000, 0x80, 0x00]));
+    // 2.5V in the +-10V range, loaded before the range is switched
+    trans.extend(write([0b00000000, 0xA0, 0x00]));
+    trans.extend(write([0b00011101, 0x00, 0x00]));
+    trans.extend(write([0b00001000, 0x00, 0b100]));
+    let spi = MockSpi::new(&trans);
+
+    let mut dac = Ad57xxShared::new_ad57x4(spi);
+    dac.set_dac_output(ChannelQuad::DacA, 0x8000).unwrap();
+    dac.change_range_safely(ChannelQuad::DacA, OutputRange::Bipolar10V, RangeRoute::Direct)
+        .unwrap();
+    dac.destroy().done();
+}
+
+#[test]
+fn direct_switches_range_before_narrowing_code() {
+    let mut trans = vec![];
+    trans.extend(write([0b00001001, 0x00, 0b100]));
+    trans.extend(write([0b00000001, 0xA0, 0x00]));
+    // 2.5V in the 0V to 5V range
+    trans.extend(write([0b00001001, 0x00, 0b000]));
+    trans.extend(write([0b00000001, 0x80, 0x00]));
+    trans.extend(write([0b00011101, 0x00, 0x00]));
+    let spi = MockSpi::new(&trans);
+
+    let mut dac = Ad57xxShared::new_ad57x4(spi);
+    dac.set_output_range(ChannelQuad::DacB, OutputRange::Bipolar10V)
+        .unwrap();
+    dac.set_dac_output(ChannelQuad::DacB, 0xA000).unwrap();
+    dac.change_range_safely(ChannelQuad::DacB, OutputRange::Unipolar5V, RangeRoute::Direct)
+        .unwrap();
+    dac.destroy().done();
+}
+
+#[test]
+fn power_down_route_clamps_and_restores_power() {
+    let mut trans = vec![];
+    trans.extend(write([0b00010000, 0x00, 0b0101]));
+    trans.extend(write([0b00001010, 0x00, 0b011]));
+    trans.extend(write([0b00000010, 0x00, 0x00]));
+    // -5V does not fit in the unipolar range and is clamped to 0V
+    trans.extend(write([0b00010000, 0x00, 0b0001]));
+    trans.extend(write([0b00001010, 0x00, 0b001]));
+    trans.extend(write([0b00000010, 0x00, 0x00]));
+    trans.extend(write([0b00011101, 0x00, 0x00]));
+    trans.extend(write([0b00010000, 0x00, 0b0101]));
+    let spi = MockSpi::new(&trans);
+
+    let mut dac = Ad57xxShared::new_ad57x2(spi);
+    dac.set_power(ChannelDual::AllDacs, true).unwrap();
+    dac.set_output_range(ChannelDual::DacB, OutputRange::Bipolar5V)
+        .unwrap();
+    dac.set_dac_output(ChannelDual::DacB, 0x0000).unwrap();
+    dac.change_range_safely(ChannelDual::DacB, OutputRange::Unipolar10V, RangeRoute::PowerDown)
+        .unwrap();
+    dac.destroy().done();
+}
+
+#[test]
+fn clear_route_restores_other_channels() {
+    let mut trans = vec![];
+    trans.extend(write([0b00000000, 0x12, 0x34]));
+    trans.extend(write([0b00000010, 0xC0, 0x00]));
+    trans.extend(write([0b00011100, 0x00, 0x00]));
+    // 3.75V on the 5V range
+    trans.extend(write([0b00001010, 0x00, 0b010]));
+    trans.extend(write([0b00000010, 0x58, 0xE4]));
+    trans.extend(write([0b00000000, 0x12, 0x34]));
+    trans.extend(write([0b00011101, 0x00, 0x00]));
+    let spi = MockSpi::new(&trans);
+
+    let mut dac = Ad57xxShared::new_ad57x2(spi);
+    dac.set_dac_output(ChannelDual::DacA, 0x1234).unwrap();
+    dac.set_dac_output(ChannelDual::DacB, 0xC000).unwrap();
+    dac.change_range_safely(ChannelDual::DacB, OutputRange::Unipolar10_8V, RangeRoute::Clear)
+        .unwrap();
+    dac.destroy().done();
+}
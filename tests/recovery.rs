@@ -0,0 +1,91 @@
+use ad57xx::ad57x2::ChannelDual;
+#[cfg(feature = "readback")]
+use ad57xx::ad57x4::ChannelQuad;
+use ad57xx::recovery::Recovery;
+#[cfg(feature = "readback")]
+use ad57xx::{model::Model, Ad57xx};
+use ad57xx::{Ad57xxShared, Config, OutputRange};
+
+mod common;
+use common::Recorder;
+#[cfg(feature = "readback")]
+use common::Shared;
+
+#[cfg(feature = "readback")]
+#[test]
+fn recover_after_reset() {
+    let model = Shared::default();
+    let mut dac = Ad57xxShared::new_ad57x4(model.clone());
+    let cfg = Config::default().with_clr_select(true);
+    dac.set_config(cfg).unwrap();
+    dac.set_output_range(ChannelQuad::DacB, OutputRange::Bipolar10V)
+        .unwrap();
+    dac.set_power(ChannelQuad::DacB, true).unwrap();
+    dac.set_dac_output(ChannelQuad::DacB, 0xC000).unwrap();
+
+    let frames = model.0.borrow().frames();
+    assert_eq!(dac.recover().unwrap(), Recovery::Healthy);
+    // Only readback frames, no writes
+    assert_eq!(model.0.borrow().frames(), frames + 2 * 10);
+
+    model.0.borrow_mut().reset();
+    assert!(dac.recover().unwrap().is_recovered());
+    let model = model.0.borrow();
+    assert_eq!(model.config(), cfg);
+    assert_eq!(model.range(1), Some(OutputRange::Bipolar10V));
+    assert_eq!(model.power() & 0xF, 0b0010);
+    assert_eq!(model.output(1), Some(0xC000));
+}
+
+#[cfg(feature = "readback")]
+#[test]
+fn detect_reset_of_dac_registers_only() {
+    // Powered down with default configuration and ranges, a reset only
+    // clears the DAC registers
+    let model = Shared::default();
+    let mut dac = Ad57xxShared::new_ad57x4(model.clone());
+    dac.set_dac_output(ChannelQuad::DacC, 0x8000).unwrap();
+    assert_eq!(dac.recover().unwrap(), Recovery::Healthy);
+
+    model.0.borrow_mut().reset();
+    assert!(dac.recover().unwrap().is_recovered());
+    assert_eq!(model.0.borrow().dac_register(2), Some(0x8000));
+}
+
+#[cfg(feature = "readback")]
+#[test]
+fn detect_needs_sdo() {
+    use ad57xx::Error;
+
+    let mut dac = Ad57xxShared::new_ad57x4(Model::ad57x4());
+    dac.set_config(Config::default().with_sdo_disable(true))
+        .unwrap();
+    assert!(matches!(dac.recover(), Err(Error::InvalidArgument)));
+}
+
+#[test]
+fn recover_with_hook() {
+    let mut dac = Ad57xxShared::new_ad57x2(Recorder::default());
+    dac.set_output_range(ChannelDual::DacB, OutputRange::Unipolar10V)
+        .unwrap();
+    dac.set_dac_output(ChannelDual::DacB, 0x1234).unwrap();
+    assert_eq!(dac.recover_with(|| false).unwrap(), Recovery::Healthy);
+    assert_eq!(dac.recover_with(|| true).unwrap(), Recovery::Recovered);
+    assert_eq!(
+        dac.destroy().frames,
+        [
+            vec![0x0A, 0x00, 0x01],
+            vec![0x02, 0x12, 0x34],
+            // Re-initialisation, the outputs stay powered down until the
+            // ranges and codes are loaded
+            vec![0x19, 0x00, u8::from(Config::default())],
+            vec![0x10, 0x00, 0x00],
+            vec![0x08, 0x00, 0x00],
+            vec![0x00, 0x00, 0x00],
+            vec![0x0A, 0x00, 0x01],
+            vec![0x02, 0x12, 0x34],
+            vec![0x1D, 0x00, 0x00],
+            vec![0x10, 0x00, 0x00],
+        ]
+    );
+}
@@ -0,0 +1,105 @@
+use ad57xx::ad57x2::{ChannelDual, PowerConfigDual};
+use ad57xx::ad57x4::ChannelQuad;
+use ad57xx::model::Model;
+use ad57xx::register::{Clear, Control, DacReg, Load, Nop, PowerReg, RangeReg};
+use ad57xx::{Ad57xx, Ad57xxShared, Command, Config, Data, Error, Function, OutputRange};
+
+mod common;
+use common::Recorder;
+
+#[test]
+fn typed_writes() {
+    let mut dac = Ad57xxShared::new_ad57x4(Recorder::default());
+    dac.write_register(DacReg(ChannelQuad::DacC), 0xBEEF)
+        .unwrap();
+    dac.write_register(RangeReg(ChannelQuad::AllDacs), OutputRange::Bipolar10_8V)
+        .unwrap();
+    dac.write_register(PowerReg, 0x000Fu16.into()).unwrap();
+    dac.write_register(Control::<Config>::new(), Config::default())
+        .unwrap();
+    dac.write_register(Control::<Nop>::new(), ()).unwrap();
+    dac.write_register(Control::<Clear>::new(), ()).unwrap();
+    dac.write_register(Control::<Load>::new(), ()).unwrap();
+    assert_eq!(
+        dac.destroy().frames,
+        [
+            [0x02, 0xBE, 0xEF],
+            [0x0C, 0x00, 0x05],
+            [0x10, 0x00, 0x0F],
+            [0x19, 0x00, u8::from(Config::default())],
+            [0x18, 0x00, 0x00],
+            [0x1C, 0x00, 0x00],
+            [0x1D, 0x00, 0x00],
+        ]
+    );
+}
+
+#[test]
+fn dynamic_write_matches_typed() {
+    let mut typed = Ad57xxShared::new_ad57x2(Recorder::default());
+    typed
+        .write_register(DacReg(ChannelDual::DacB), 0x1234)
+        .unwrap();
+    typed
+        .write_register(PowerReg, PowerConfigDual::from(0x0005))
+        .unwrap();
+    typed.write_register(Control::<Load>::new(), ()).unwrap();
+
+    let mut dynamic = Ad57xxShared::new_ad57x2(Recorder::default());
+    dynamic
+        .write(
+            Command::DacRegister(ChannelDual::DacB),
+            Data::DacValue(0x1234),
+        )
+        .unwrap();
+    dynamic
+        .write(
+            Command::PowerControlRegister,
+            Data::PowerControl(PowerConfigDual::from(0x0005)),
+        )
+        .unwrap();
+    dynamic
+        .write(Command::ControlRegister(Function::Load), Data::None)
+        .unwrap();
+
+    assert_eq!(typed.destroy().frames, dynamic.destroy().frames);
+}
+
+#[test]
+fn rejects_invalid_range() {
+    let mut dac = Ad57xxShared::new_ad57x4(Recorder::default());
+    let result = dac.write_register(RangeReg(ChannelQuad::DacA), OutputRange::InvalidReadback);
+    assert!(matches!(result, Err(Error::InvalidArgument)));
+    let result = dac.write(
+        Command::RangeSelectRegister(ChannelQuad::AllDacs),
+        Data::OutputRange(OutputRange::InvalidReadback),
+    );
+    assert!(matches!(result, Err(Error::InvalidArgument)));
+    assert!(dac.destroy().frames.is_empty());
+}
+
+#[test]
+fn typed_readback() {
+    let mut dac = Ad57xxShared::new_ad57x4(Model::ad57x4());
+    dac.set_output_range(ChannelQuad::DacB, OutputRange::Bipolar5V)
+        .unwrap();
+    dac.set_dac_output(ChannelQuad::DacB, 0x4321).unwrap();
+    dac.set_power(ChannelQuad::DacB, true).unwrap();
+
+    assert_eq!(
+        dac.read_register(DacReg(ChannelQuad::DacB)).unwrap(),
+        0x4321
+    );
+    assert_eq!(
+        dac.read_register(RangeReg(ChannelQuad::DacB)).unwrap(),
+        OutputRange::Bipolar5V
+    );
+    assert_eq!(
+        u16::from(dac.read_register(PowerReg).unwrap()) & 0xF,
+        0b0010
+    );
+    assert_eq!(
+        dac.read_register(Control::<Config>::new()).unwrap(),
+        Config::default()
+    );
+}
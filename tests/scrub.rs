@@ -0,0 +1,63 @@
+#![cfg(feature = "readback")]
+use ad57xx::scrub::ScrubMode;
+use ad57xx::{Ad57xxShared, Command};
+use embedded_hal_mock::eh1::spi::Mock as MockSpi;
+
+mod common;
+use common::{read, write};
+
+#[test]
+fn scrub_repairs_dac_register() {
+    let mut trans = vec![];
+    trans.extend(write([0b00000001, 0x12, 0x34]));
+    // DAC A matches the reset value
+    trans.extend(read(0b10000000, 0x0000));
+    // DAC B was reset and gets rewritten
+    trans.extend(read(0b10000001, 0x0000));
+    trans.extend(write([0b00000001, 0x12, 0x34]));
+    let spi = MockSpi::new(&trans);
+
+    let mut dac = Ad57xxShared::new_ad57x4(spi);
+    dac.set_dac_output(ad57xx::ad57x4::ChannelQuad::DacB, 0x1234)
+        .unwrap();
+    let report = dac.scrub(2, ScrubMode::Repair).unwrap();
+    assert_eq!(report.checked, 2);
+    assert_eq!(report.drifted, 1);
+    let drift = report.first.unwrap();
+    assert!(matches!(
+        drift.register,
+        Command::DacRegister(ad57xx::ad57x4::ChannelQuad::DacB)
+    ));
+    assert_eq!(drift.expected, 0x1234);
+    assert_eq!(drift.actual, 0x0000);
+    dac.destroy().done();
+}
+
+#[test]
+fn scrub_reports_power_and_wraps_around() {
+    let mut trans = vec![];
+    trans.extend(write([0b00010000, 0x00, 0b0101]));
+    // DAC A, DAC B, range A, range B
+    trans.extend(read(0b10000000, 0x0000));
+    trans.extend(read(0b10000010, 0x0000));
+    trans.extend(read(0b10001000, 0x0000));
+    trans.extend(read(0b10001010, 0x0000));
+    // Power control lost its power-up bits, the TSD flag is ignored
+    trans.extend(read(0b10010000, 0b0010_0000));
+    // Control register, default configuration
+    trans.extend(read(0b10011001, 0x0004));
+    // Wrap around to DAC A
+    trans.extend(read(0b10000000, 0x0000));
+    let spi = MockSpi::new(&trans);
+
+    let mut dac = Ad57xxShared::new_ad57x2(spi);
+    dac.set_power(ad57xx::ad57x2::ChannelDual::AllDacs, true)
+        .unwrap();
+    let report = dac.scrub(dac.scrub_len() + 1, ScrubMode::Report).unwrap();
+    assert_eq!(report.checked, 7);
+    assert_eq!(report.drifted, 1);
+    let drift = report.first.unwrap();
+    assert!(matches!(drift.register, Command::PowerControlRegister));
+    assert_eq!(drift.expected, 0b0101);
+    dac.destroy().done();
+}
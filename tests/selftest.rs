@@ -0,0 +1,144 @@
+#![cfg(feature = "readback")]
+use ad57xx::ad57x4::ChannelQuad;
+use ad57xx::model::Model;
+use ad57xx::selftest::SelfTest;
+use ad57xx::{Ad57xx, Ad57xxShared, Command, Config, Function, OutputRange};
+use embedded_hal::spi::{ErrorType, Operation, SpiDevice};
+
+/// Device whose SDO line shows `stuck` bits of the low data byte set, or
+/// nothing but the `idle` level without a model
+struct Faulty {
+    model: Option<Model<ad57xx::marker::Ad57x4>>,
+    stuck: u8,
+    idle: u8,
+}
+
+impl ErrorType for Faulty {
+    type Error = core::convert::Infallible;
+}
+
+impl SpiDevice for Faulty {
+    fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), Self::Error> {
+        let Some(model) = &mut self.model else {
+            for op in operations {
+                if let Operation::Transfer(rx, _) = op {
+                    rx.fill(self.idle);
+                }
+            }
+            return Ok(());
+        };
+        model.transaction(operations)?;
+        for op in operations {
+            if let Operation::Transfer(rx, _) = op {
+                rx[2] |= self.stuck;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 12 bit part, the low nibble of the DAC register reads back as garbage
+struct TwelveBit(Model<ad57xx::marker::Ad57x4>);
+
+impl ErrorType for TwelveBit {
+    type Error = core::convert::Infallible;
+}
+
+impl SpiDevice for TwelveBit {
+    fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), Self::Error> {
+        self.0.transaction(operations)?;
+        for op in operations {
+            // The header echoes the read command of the DAC register
+            if let Operation::Transfer(rx, _) = op {
+                if rx[0] & 0b1011_1000 == 0b1000_0000 {
+                    rx[2] ^= 0x0F;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn pass_and_restore() {
+    let mut dac = Ad57xxShared::new_ad57x4(Model::ad57x4());
+    dac.set_output_range(ChannelQuad::DacC, OutputRange::Bipolar10V)
+        .unwrap();
+    dac.set_dac_output(ChannelQuad::DacC, 0x1234).unwrap();
+    let result = dac.self_test().unwrap();
+    assert!(result.is_pass(), "{result:?}");
+
+    let model = dac.destroy();
+    assert_eq!(model.dac_register(2), Some(0x1234));
+    assert_eq!(model.dac_register(0), Some(0x0000));
+    assert_eq!(model.range(2), Some(OutputRange::Bipolar10V));
+    assert_eq!(model.config(), Config::default());
+}
+
+#[test]
+fn restores_device_values() {
+    // Registers written by an earlier driver instance, unknown to this one
+    let mut dac = Ad57xxShared::new_ad57x4(Model::ad57x4());
+    dac.set_output_range(ChannelQuad::DacB, OutputRange::Bipolar5V)
+        .unwrap();
+    dac.set_dac_output(ChannelQuad::DacB, 0x4321).unwrap();
+    dac.set_config(Config::default().with_clr_select(true))
+        .unwrap();
+
+    let mut dac = Ad57xxShared::new_ad57x4(dac.destroy());
+    assert!(dac.self_test().unwrap().is_pass());
+    let model = dac.destroy();
+    assert_eq!(model.dac_register(1), Some(0x4321));
+    assert_eq!(model.range(1), Some(OutputRange::Bipolar5V));
+    assert!(model.config().clr_select());
+}
+
+#[test]
+fn twelve_bit_part_passes() {
+    let mut dac = Ad57xxShared::new_ad57x4(TwelveBit(Model::ad57x4()));
+    assert!(dac.self_test().unwrap().is_pass());
+}
+
+#[test]
+fn sdo_disabled() {
+    let mut dac = Ad57xxShared::new_ad57x4(Model::ad57x4());
+    let cfg = Config::default().with_sdo_disable(true);
+    dac.set_config(cfg).unwrap();
+    assert!(matches!(dac.self_test().unwrap(), SelfTest::SdoDisabled));
+    assert_eq!(dac.destroy().config(), cfg);
+}
+
+#[test]
+fn no_response_and_stuck_bits() {
+    for idle in [0x00, 0xFF] {
+        let mut dac = Ad57xxShared::new_ad57x4(Faulty {
+            model: None,
+            stuck: 0,
+            idle,
+        });
+        let level = u16::from_le_bytes([idle, idle]);
+        assert!(matches!(
+            dac.self_test().unwrap(),
+            SelfTest::NoResponse { level: l } if l == level
+        ));
+    }
+
+    let mut dac = Ad57xxShared::new_ad57x4(Faulty {
+        model: Some(Model::ad57x4()),
+        stuck: 0x08,
+        idle: 0,
+    });
+    let SelfTest::StuckBits {
+        register,
+        stuck_high,
+        stuck_low,
+    } = dac.self_test().unwrap()
+    else {
+        panic!("stuck bit not detected");
+    };
+    assert!(matches!(
+        register,
+        Command::ControlRegister(Function::Config)
+    ));
+    assert_eq!((stuck_high, stuck_low), (0x0008, 0));
+}
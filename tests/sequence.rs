@@ -0,0 +1,121 @@
+use ad57xx::ad57x4::ChannelQuad;
+use ad57xx::model::Model;
+use ad57xx::sequence::{Executor, ParseErrorKind, Position, Sequence, Step, Value};
+use ad57xx::{Ad57xxShared, Error, OutputRange};
+use embedded_hal::delay::DelayNs;
+
+/// Delay summing up the requested time
+#[derive(Default)]
+struct Clock {
+    ns: u64,
+}
+
+impl DelayNs for Clock {
+    fn delay_ns(&mut self, ns: u32) {
+        self.ns += ns as u64;
+    }
+}
+
+const STIMULUS: &[u8] = b"# stimulus
+range all bi10
+power all on\r
+set b 0x8000   # midscale
+set a -2.5V
+wait 10ms
+ramp a 0x0000 0x1000 1ms 4
+load
+";
+
+#[test]
+fn parse() {
+    let seq = Sequence::parse(STIMULUS).unwrap();
+    let steps: Vec<_> = seq.steps().collect();
+    assert_eq!(steps.len(), 7);
+    assert_eq!(
+        steps[4],
+        (Position { line: 6, column: 1 }, Step::Wait { us: 10_000 })
+    );
+    assert!(matches!(
+        steps[3].1,
+        Step::Set {
+            value: Value::Microvolts(-2_500_000),
+            ..
+        }
+    ));
+
+    let error = |text: &[u8]| Sequence::parse(text).unwrap_err();
+    let err = error(b"load\n  set e 1V");
+    assert_eq!(err.position, Position { line: 2, column: 7 });
+    assert_eq!(err.kind, ParseErrorKind::InvalidChannel);
+    assert_eq!(error(b"jump a").kind, ParseErrorKind::UnknownCommand);
+    let err = error(b"set a");
+    assert_eq!(err.position, Position { line: 1, column: 6 });
+    assert_eq!(err.kind, ParseErrorKind::MissingArgument);
+    assert_eq!(error(b"load now").kind, ParseErrorKind::UnexpectedArgument);
+    assert_eq!(
+        error(b"set a 1.2345678V").kind,
+        ParseErrorKind::InvalidValue
+    );
+    assert_eq!(
+        error(b"ramp a 0 1V 1ms 2").kind,
+        ParseErrorKind::InvalidValue
+    );
+    assert_eq!(
+        error(b"ramp a 0 1 1ms 0").kind,
+        ParseErrorKind::InvalidSteps
+    );
+    assert_eq!(error(b"wait 10").kind, ParseErrorKind::InvalidDuration);
+}
+
+#[test]
+fn execute() {
+    let seq = Sequence::parse(STIMULUS).unwrap();
+    let mut model = Model::ad57x2();
+    model.set_ldac(true);
+    let mut dac = Ad57xxShared::new_ad57x2(model);
+    let mut exec = Executor::new(Clock::default());
+    exec.run(&mut dac, &seq).unwrap();
+    assert_eq!(exec.destroy().ns, 11_000_000);
+
+    let model = dac.destroy();
+    assert_eq!(model.range(0), Some(OutputRange::Bipolar10V));
+    assert_eq!(model.power(), 0b0101);
+    assert_eq!(model.output(0), Some(0x1000));
+    assert_eq!(model.output(2), Some(0x8000));
+    // range, power, 2 sets, 5 ramp steps and load, plus the power control
+    // readback of the power step
+    let reads = if cfg!(feature = "readback") { 2 } else { 0 };
+    assert_eq!(model.frames(), 10 + reads);
+}
+
+#[test]
+fn power_keeps_other_channels() {
+    let mut dac = Ad57xxShared::new_ad57x4(Model::ad57x4());
+    dac.set_power(ChannelQuad::DacB | ChannelQuad::DacD, true)
+        .unwrap();
+    let seq = Sequence::parse(b"power a on\npower d off").unwrap();
+    Executor::new(Clock::default()).run(&mut dac, &seq).unwrap();
+    assert_eq!(dac.destroy().power() & 0xF, 0b0011);
+}
+
+#[test]
+fn execution_errors() {
+    let mut dac = Ad57xxShared::new_ad57x2(Model::ad57x2());
+    let mut exec = Executor::new(Clock::default());
+
+    // Voltages need a known range
+    let seq = Sequence::parse(b"set a 0x10\nset a 1V").unwrap();
+    let err = exec.run(&mut dac, &seq).unwrap_err();
+    assert_eq!(err.position, Position { line: 2, column: 1 });
+    assert!(matches!(err.error, Error::InvalidArgument));
+
+    let mut exec = exec.with_range(0, OutputRange::Unipolar5V);
+    exec.run(&mut dac, &seq).unwrap();
+    assert_eq!(dac.destroy().dac_register(0), Some(0x3333));
+
+    // A dual channel part has no channel C
+    let mut dac = Ad57xxShared::new_ad57x2(Model::ad57x2());
+    let seq = Sequence::parse(b"set c 0").unwrap();
+    let err = exec.run(&mut dac, &seq).unwrap_err();
+    assert!(matches!(err.error, Error::InvalidArgument));
+}
@@ -0,0 +1,78 @@
+#![cfg(feature = "serde")]
+use ad57xx::ad57x2::PowerConfigDual;
+use ad57xx::ad57x4::{ChannelQuad, PowerConfigQuad};
+use ad57xx::cv::{Calibration, Scale};
+use ad57xx::state::DeviceState;
+use ad57xx::{Config, OutputRange};
+
+fn state() -> DeviceState {
+    DeviceState {
+        codes: [0x1234, 0, 0xABCD, 0xFFFF],
+        ranges: [
+            OutputRange::Bipolar10V,
+            OutputRange::Unipolar5V,
+            OutputRange::Unipolar10_8V,
+            OutputRange::Bipolar5V,
+        ],
+        power: 0b0101,
+        config: Config::new().with_clr_select(true),
+    }
+}
+
+#[test]
+fn json_round_trip() {
+    let json = serde_json::to_string(&state()).unwrap();
+    assert_eq!(
+        json,
+        r#"{"codes":[4660,0,43981,65535],"ranges":["Bipolar10V","Unipolar5V","Unipolar10_8V","Bipolar5V"],"power":5,"config":6}"#
+    );
+    assert_eq!(serde_json::from_str::<DeviceState>(&json).unwrap(), state());
+
+    let cal = Calibration {
+        scale_ppm: -120,
+        offset_uv: 350,
+    };
+    let json = serde_json::to_string(&cal).unwrap();
+    assert_eq!(serde_json::from_str::<Calibration>(&json).unwrap(), cal);
+    let json = serde_json::to_string(&ChannelQuad::DacC).unwrap();
+    assert_eq!(json, r#""DacC""#);
+}
+
+#[test]
+fn postcard_round_trip() {
+    let bytes = postcard::to_allocvec(&state()).unwrap();
+    assert_eq!(
+        postcard::from_bytes::<DeviceState>(&bytes).unwrap(),
+        state()
+    );
+
+    let pcfg = PowerConfigQuad::from(0b0111_1010_1111);
+    let bytes = postcard::to_allocvec(&pcfg).unwrap();
+    let decoded: PowerConfigQuad = postcard::from_bytes(&bytes).unwrap();
+    assert_eq!(u16::from(decoded), u16::from(pcfg));
+
+    // The configuration is a single byte both ways
+    let cfg = Config::new().with_sdo_disable(true);
+    let bytes = postcard::to_allocvec(&cfg).unwrap();
+    assert_eq!(bytes, [u8::from(cfg)]);
+    assert_eq!(postcard::from_bytes::<Config>(&bytes).unwrap(), cfg);
+}
+
+#[test]
+fn rejects_invalid_values() {
+    assert!(serde_json::to_string(&OutputRange::InvalidReadback).is_err());
+    assert!(serde_json::from_str::<OutputRange>(r#""InvalidReadback""#).is_err());
+    // Reserved bits set
+    assert!(serde_json::from_str::<Config>("16").is_err());
+    assert!(serde_json::from_str::<PowerConfigQuad>("16").is_err());
+    assert!(serde_json::from_str::<PowerConfigDual>("2").is_err());
+    assert!(serde_json::from_str::<Scale>(r#"{"Custom":4096}"#).is_err());
+    let json = r#"{"codes":[0,0,0,0],"ranges":["Unipolar5V","Unipolar5V","Unipolar5V","Unipolar5V"],"power":16,"config":4}"#;
+    assert!(serde_json::from_str::<DeviceState>(json).is_err());
+    // Valid values are accepted
+    assert_eq!(serde_json::from_str::<Config>("4").unwrap(), Config::new());
+    assert_eq!(
+        serde_json::from_str::<Scale>(r#"{"Custom":145}"#).unwrap(),
+        Scale::Custom(145)
+    );
+}
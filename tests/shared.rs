@@ -0,0 +1,66 @@
+#![cfg(feature = "critical-section")]
+use ad57xx::ad57x2::ChannelDual;
+use ad57xx::ad57x4::ChannelQuad;
+use ad57xx::model::Model;
+use ad57xx::shared::SharedAd57xx;
+use ad57xx::{Ad57xxShared, Error, OutputRange};
+use embedded_hal_mock::eh1::spi::Mock as MockSpi;
+
+mod common;
+use common::write;
+
+#[test]
+fn one_frame_per_operation() {
+    let mut trans = vec![];
+    trans.extend(write([0b00001010, 0x00, 0b100]));
+    trans.extend(write([0b00010000, 0x00, 0b0101]));
+    // One frame per channel for voltages on all channels
+    trans.extend(write([0b00000000, 0x00, 0x00]));
+    trans.extend(write([0b00000010, 0x80, 0x00]));
+    trans.extend(write([0b00011101, 0x00, 0x00]));
+    let shared = SharedAd57xx::new(Ad57xxShared::new_ad57x2(MockSpi::new(&trans)));
+    shared
+        .set_output_range(ChannelDual::DacB, OutputRange::Bipolar10V)
+        .unwrap();
+    shared.set_power(ChannelDual::AllDacs, true).unwrap();
+    shared.set_dac_microvolts(ChannelDual::AllDacs, 0).unwrap();
+    shared.load_dacs().unwrap();
+    assert_eq!(shared.dac_output(ChannelDual::DacB).unwrap(), 0x8000);
+    shared.into_inner().destroy().done();
+}
+
+#[test]
+fn split_handles_across_threads() {
+    let shared = SharedAd57xx::new(Ad57xxShared::new_ad57x4(Model::ad57x4()));
+    let handles = shared.split();
+    std::thread::scope(|s| {
+        for (i, handle) in handles.into_iter().enumerate() {
+            s.spawn(move || {
+                handle.set_power(true).unwrap();
+                for code in 0..100 {
+                    handle.set_code(code * (i as u16 + 1)).unwrap();
+                }
+            });
+        }
+    });
+    assert_eq!(handles[2].channel(), ChannelQuad::DacC);
+    assert_eq!(handles[2].code().unwrap(), 99 * 3);
+    let model = shared.into_inner().destroy();
+    assert_eq!(model.power(), 0b1111);
+    assert_eq!(model.output(3), Some(99 * 4));
+    assert_eq!(model.frames(), 404);
+}
+
+#[test]
+fn voltage_rejected_as_a_whole() {
+    let shared = SharedAd57xx::new(Ad57xxShared::new_ad57x2(Model::ad57x2()));
+    shared
+        .set_output_range(ChannelDual::DacA, OutputRange::Bipolar5V)
+        .unwrap();
+    // Channel B is unipolar, channel A is not written either
+    assert!(matches!(
+        shared.set_dac_microvolts(ChannelDual::AllDacs, -1_000_000),
+        Err(Error::InvalidArgument)
+    ));
+    assert_eq!(shared.into_inner().destroy().frames(), 1);
+}
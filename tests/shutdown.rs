@@ -0,0 +1,199 @@
+use std::cell::RefCell;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::rc::Rc;
+
+use ad57xx::ad57x2::ChannelDual;
+use ad57xx::ad57x4::ChannelQuad;
+use ad57xx::coding::Coding;
+use ad57xx::model::Model;
+use ad57xx::shutdown::{Park, Shutdown, ShutdownGuard};
+use ad57xx::{Ad57xxShared, Error, OutputRange};
+use embedded_hal::delay::DelayNs;
+use embedded_hal::spi::{ErrorType, Operation, SpiDevice};
+
+mod common;
+use common::{Recorder, Shared};
+
+/// Recorder that stays accessible while the driver owns it
+#[derive(Clone, Default)]
+struct SharedRecorder(Rc<RefCell<Recorder>>);
+
+impl ErrorType for SharedRecorder {
+    type Error = embedded_hal::spi::ErrorKind;
+}
+
+impl SpiDevice for SharedRecorder {
+    fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), Self::Error> {
+        self.0.borrow_mut().transaction(operations)
+    }
+}
+
+/// Delay summing up the requested time
+#[derive(Default)]
+struct Clock {
+    ns: u64,
+}
+
+impl DelayNs for Clock {
+    fn delay_ns(&mut self, ns: u32) {
+        self.ns += ns as u64;
+    }
+}
+
+#[test]
+fn shutdown_sequence() {
+    let mut dac = Ad57xxShared::new_ad57x4(Model::ad57x4());
+    dac.set_output_range(ChannelQuad::AllDacs, OutputRange::Bipolar10V)
+        .unwrap();
+    dac.set_power(ChannelQuad::AllDacs, true).unwrap();
+    dac.set_dac_output(ChannelQuad::DacD, 0xFFFF).unwrap();
+    let plan = Shutdown {
+        park: Park::Codes([0x8000; 4]),
+        disable_sdo: true,
+    };
+    dac.shutdown(&plan, &mut Clock::default()).unwrap();
+    let model = dac.destroy();
+    assert_eq!(model.dac_register(3), Some(0x8000));
+    assert_eq!(model.power() & 0xF, 0);
+    assert!(model.config().sdo_disable());
+}
+
+#[test]
+fn ramp() {
+    let mut dac = Ad57xxShared::new_ad57x2(Recorder::default());
+    dac.set_dac_output(ChannelDual::DacA, 0x0400).unwrap();
+    let mut plan = Shutdown {
+        park: Park::Ramp {
+            codes: [0x0000, 0x0200, 0, 0],
+            steps: 0,
+            step_us: 100,
+        },
+        disable_sdo: false,
+    };
+    let mut clock = Clock::default();
+    assert!(matches!(
+        dac.shutdown(&plan, &mut clock),
+        Err(Error::InvalidArgument)
+    ));
+    if let Park::Ramp { steps, .. } = &mut plan.park {
+        *steps = 2;
+    }
+    dac.shutdown(&plan, &mut clock).unwrap();
+    // The delay follows each step
+    assert_eq!(clock.ns, 200_000);
+    assert_eq!(
+        dac.destroy().frames,
+        [
+            vec![0x00, 0x04, 0x00],
+            vec![0x00, 0x02, 0x00],
+            vec![0x02, 0x01, 0x00],
+            vec![0x1D, 0x00, 0x00],
+            vec![0x00, 0x00, 0x00],
+            vec![0x02, 0x02, 0x00],
+            vec![0x1D, 0x00, 0x00],
+            vec![0x10, 0x00, 0x00],
+        ]
+    );
+}
+
+#[test]
+fn ramp_full_scale_many_steps() {
+    let mut dac = Ad57xxShared::new_ad57x2(Recorder::default());
+    dac.set_dac_output(ChannelDual::DacA, 0xFFFF).unwrap();
+    let plan = Shutdown {
+        park: Park::Ramp {
+            codes: [0x0000; 4],
+            steps: u16::MAX,
+            step_us: 0,
+        },
+        disable_sdo: false,
+    };
+    dac.shutdown(&plan, &mut Clock::default()).unwrap();
+    let frames = dac.destroy().frames;
+    // Channel A steps down by one code per step, without wrapping
+    let codes: Vec<u16> = frames[1..]
+        .iter()
+        .filter(|f| f[0] == 0x00)
+        .map(|f| (f[1] as u16) << 8 | f[2] as u16)
+        .collect();
+    assert_eq!(codes.len(), u16::MAX as usize);
+    assert!(codes.iter().rev().copied().eq(0..u16::MAX));
+}
+
+#[test]
+fn ramp_twos_complement() {
+    let mut dac = Ad57xxShared::new_ad57x2(Recorder::default());
+    dac.set_output_range(ChannelDual::DacA, OutputRange::Bipolar10V)
+        .unwrap();
+    dac.set_coding(Coding::TwosComplement);
+    dac.set_dac_output(ChannelDual::DacA, 0xF000).unwrap();
+    let plan = Shutdown {
+        park: Park::Ramp {
+            codes: [0x1000, 0x0000, 0, 0],
+            steps: 2,
+            step_us: 100,
+        },
+        disable_sdo: false,
+    };
+    dac.shutdown(&plan, &mut Clock::default()).unwrap();
+    let frames = dac.destroy().frames;
+    // The midpoint of -4096 and 4096 is 0, not negative full scale 0x8000
+    assert_eq!(
+        frames[2..],
+        [
+            vec![0x00, 0x00, 0x00],
+            vec![0x02, 0x00, 0x00],
+            vec![0x1D, 0x00, 0x00],
+            vec![0x00, 0x10, 0x00],
+            vec![0x02, 0x00, 0x00],
+            vec![0x1D, 0x00, 0x00],
+            vec![0x10, 0x00, 0x00],
+        ]
+    );
+}
+
+#[test]
+fn guard_shuts_down_on_panic() {
+    let spi = Shared::default();
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        let dac = Ad57xxShared::new_ad57x4(spi.clone());
+        let mut dac = ShutdownGuard::new(dac, Shutdown::default(), Clock::default());
+        dac.set_power(ChannelQuad::AllDacs, true).unwrap();
+        dac.set_dac_output(ChannelQuad::DacA, 0xFFFF).unwrap();
+        panic!("test sequence failed");
+    }));
+    assert!(result.is_err());
+    assert_eq!(spi.0.borrow().power() & 0xF, 0);
+    assert_eq!(spi.0.borrow().dac_register(0), Some(0x0000));
+
+    // A disarmed guard leaves the outputs alone
+    let dac = Ad57xxShared::new_ad57x4(spi.clone());
+    let mut dac = ShutdownGuard::new(dac, Shutdown::default(), Clock::default());
+    dac.set_power(ChannelQuad::DacB, true).unwrap();
+    drop(dac.disarm());
+    assert_eq!(spi.0.borrow().power() & 0xF, 0b0010);
+}
+
+#[test]
+fn guard_stays_armed_on_failure() {
+    let spi = SharedRecorder::default();
+    let dac = Ad57xxShared::new_ad57x4(spi.clone());
+    let mut dac = ShutdownGuard::new(dac, Shutdown::default(), Clock::default());
+    spi.0.borrow_mut().fail = true;
+    assert!(matches!(dac.shutdown(), Err(Error::Spi(_))));
+    spi.0.borrow_mut().fail = false;
+    // Dropping the guard retries the sequence
+    drop(dac);
+    assert_eq!(
+        spi.0.borrow().frames,
+        [vec![0x1C, 0x00, 0x00], vec![0x10, 0x00, 0x00]]
+    );
+
+    // A successful shutdown disarms the guard
+    let spi = SharedRecorder::default();
+    let dac = Ad57xxShared::new_ad57x4(spi.clone());
+    let mut dac = ShutdownGuard::new(dac, Shutdown::default(), Clock::default());
+    dac.shutdown().unwrap();
+    drop(dac);
+    assert_eq!(spi.0.borrow().frames.len(), 2);
+}
@@ -0,0 +1,92 @@
+use ad57xx::state::DeviceState;
+use ad57xx::{Ad57xxShared, Config, OutputRange};
+use embedded_hal_mock::eh1::spi::Mock as MockSpi;
+
+mod common;
+#[cfg(feature = "readback")]
+use common::read;
+use common::write;
+
+fn state() -> DeviceState {
+    DeviceState {
+        codes: [0x1234, 0, 0xABCD, 0],
+        ranges: [
+            OutputRange::Bipolar10V,
+            OutputRange::Unipolar5V,
+            OutputRange::Unipolar10V,
+            OutputRange::Unipolar5V,
+        ],
+        power: 0b0101,
+        config: Config::new().with_clr_select(true),
+    }
+}
+
+#[test]
+fn restore_sequence() {
+    let mut trans = vec![];
+    trans.extend(write([0b00011001, 0x00, 0b0110]));
+    // Power down
+    trans.extend(write([0b00010000, 0x00, 0x00]));
+    // Range and code of channel A and B
+    trans.extend(write([0b00001000, 0x00, 0b100]));
+    trans.extend(write([0b00000000, 0x12, 0x34]));
+    trans.extend(write([0b00001010, 0x00, 0b001]));
+    trans.extend(write([0b00000010, 0xAB, 0xCD]));
+    // Load and power up
+    trans.extend(write([0b00011101, 0x00, 0x00]));
+    trans.extend(write([0b00010000, 0x00, 0b0101]));
+    let spi = MockSpi::new(&trans);
+
+    let mut dac = Ad57xxShared::new_ad57x2(spi);
+    dac.restore(&state()).unwrap();
+    dac.destroy().done();
+}
+
+#[cfg(feature = "readback")]
+#[test]
+fn snapshot_reads_back_every_register() {
+    let mut trans = vec![];
+    for (cmd, value) in [
+        (0b10000000, 0x1234),
+        (0b10001000, 0b100),
+        (0b10000010, 0xABCD),
+        (0b10001010, 0b001),
+        // Power control with the thermal shutdown flag set
+        (0b10010000, 0b0010_0101),
+        (0b10011001, 0b0110),
+    ] {
+        trans.extend(read(cmd, value));
+    }
+    let spi = MockSpi::new(&trans);
+
+    let mut dac = Ad57xxShared::new_ad57x2(spi);
+    assert_eq!(dac.snapshot().unwrap(), state());
+    dac.destroy().done();
+}
+
+#[cfg(feature = "readback")]
+#[test]
+fn snapshot_with_sdo_disabled() {
+    use ad57xx::ad57x4::ChannelQuad;
+    use ad57xx::model::Model;
+    use ad57xx::Ad57xx;
+
+    let mut dac = Ad57xxShared::new_ad57x4(Model::ad57x4());
+    let config = Config::default().with_sdo_disable(true);
+    dac.set_config(config).unwrap();
+    dac.set_dac_output(ChannelQuad::DacC, 0x4321).unwrap();
+    let state = dac.snapshot().unwrap();
+    assert_eq!(state.codes, [0, 0, 0x4321, 0]);
+    assert_eq!(state.config, config);
+    // Nothing was read
+    assert_eq!(dac.destroy().frames(), 2);
+}
+
+#[test]
+fn serialize_roundtrip() {
+    let bytes = state().to_bytes();
+    assert_eq!(DeviceState::from_bytes(&bytes), Some(state()));
+    let mut invalid = bytes;
+    invalid[9] = 0b111;
+    assert_eq!(DeviceState::from_bytes(&invalid), None);
+}
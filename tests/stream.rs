@@ -0,0 +1,107 @@
+use ad57xx::ad57x2::ChannelDual;
+use ad57xx::ad57x4::ChannelQuad;
+use ad57xx::model::Model;
+use ad57xx::stream::{stream, DoubleBuffer, FrameEncoder, FRAME_LEN};
+use embassy_futures::block_on;
+use embedded_hal::spi::{ErrorType, SpiDevice};
+
+/// Bus recording every write, completing after a yield. Reads return the
+/// idle low level of an unconnected SDO line.
+#[derive(Default)]
+struct Recorder {
+    writes: Vec<Vec<u8>>,
+}
+
+impl ErrorType for Recorder {
+    type Error = core::convert::Infallible;
+}
+
+impl embedded_hal_async::spi::SpiBus for Recorder {
+    async fn read(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
+        words.fill(0);
+        Ok(())
+    }
+
+    async fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+        embassy_futures::yield_now().await;
+        self.writes.push(words.to_vec());
+        Ok(())
+    }
+
+    async fn transfer(&mut self, read: &mut [u8], write: &[u8]) -> Result<(), Self::Error> {
+        self.write(write).await?;
+        read.fill(0);
+        Ok(())
+    }
+
+    async fn transfer_in_place(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
+        self.write(words).await?;
+        words.fill(0);
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+#[test]
+fn encode() {
+    let encoder = FrameEncoder::new([ChannelQuad::DacA, ChannelQuad::DacC]).with_load(true);
+    assert_eq!(encoder.sample_len(), 9);
+
+    let mut samples = [[0x1234, 0xABCD], [0x0001, 0xFFFF]].into_iter();
+    // Room for one sample and a bit
+    let mut buf = [0u8; 12];
+    assert_eq!(encoder.encode(&mut samples, &mut buf), 9);
+    assert_eq!(
+        buf[..9],
+        [0x00, 0x12, 0x34, 0x02, 0xAB, 0xCD, 0x1D, 0x00, 0x00]
+    );
+    // The second sample is left for the next buffer
+    assert_eq!(samples.next(), Some([0x0001, 0xFFFF]));
+}
+
+#[test]
+fn double_buffer() {
+    let encoder = FrameEncoder::new([ChannelDual::DacB]);
+    let mut samples = (0..5).map(|i| [i]);
+    let mut buf = DoubleBuffer::<6>::new();
+    assert!(buf.front().is_empty());
+
+    assert_eq!(buf.refill(&encoder, &mut samples), 6);
+    buf.swap();
+    assert_eq!(buf.front(), [0x02, 0x00, 0x00, 0x02, 0x00, 0x01]);
+
+    let (front, back) = buf.split();
+    assert_eq!(front.len(), 6);
+    let len = encoder.encode(&mut samples, back);
+    buf.set_back_len(len);
+    buf.swap();
+    assert_eq!(buf.front(), [0x02, 0x00, 0x02, 0x02, 0x00, 0x03]);
+
+    assert_eq!(buf.refill(&encoder, &mut samples), 3);
+    buf.swap();
+    assert_eq!(buf.front(), [0x02, 0x00, 0x04]);
+}
+
+#[test]
+fn stream_to_model() {
+    let encoder = FrameEncoder::new([ChannelQuad::DacA, ChannelQuad::DacB]).with_load(true);
+    let mut samples = (0..10u16).map(|i| [i, 100 + i]);
+    let mut buf = DoubleBuffer::<{ 4 * 9 }>::new();
+    let mut bus = Recorder::default();
+    block_on(stream(&mut bus, &encoder, &mut samples, &mut buf)).unwrap();
+
+    // Four samples per buffer
+    let lens: Vec<_> = bus.writes.iter().map(Vec::len).collect();
+    assert_eq!(lens, [36, 36, 18]);
+
+    let mut model = Model::ad57x4();
+    for frame in bus.writes.concat().chunks(FRAME_LEN) {
+        model.write(frame).unwrap();
+    }
+    assert_eq!(model.frames(), 30);
+    assert_eq!(model.dac_register(0), Some(9));
+    assert_eq!(model.dac_register(1), Some(109));
+}
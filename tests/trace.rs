@@ -0,0 +1,85 @@
+//! Golden traces of every driver operation, run against the software model.
+//! Regenerate the files in `tests/golden` with `UPDATE_GOLDEN=1 cargo test`.
+#![cfg(feature = "readback")]
+use ad57xx::ad57x2::ChannelDual;
+use ad57xx::ad57x4::ChannelQuad;
+use ad57xx::model::Model;
+use ad57xx::{Ad57xx, Ad57xxShared, Command, Config, Function, OutputRange};
+
+mod common;
+use common::trace::{assert_golden, Trace};
+
+#[test]
+fn ad57x4() {
+    let spi = Trace::quad(Model::ad57x4());
+    let log = spi.log();
+    let mut dac = Ad57xxShared::new_ad57x4(spi);
+
+    log.section("set_power");
+    dac.set_power(ChannelQuad::DacB, true).unwrap();
+    dac.set_power(ChannelQuad::AllDacs, true).unwrap();
+    dac.set_power(ChannelQuad::DacD, false).unwrap();
+
+    log.section("set_output_range");
+    dac.set_output_range(ChannelQuad::AllDacs, OutputRange::Bipolar10V).unwrap();
+    dac.set_output_range(ChannelQuad::DacC, OutputRange::Unipolar10_8V).unwrap();
+
+    log.section("set_dac_output");
+    dac.set_dac_output(ChannelQuad::DacA, 0x1234).unwrap();
+    dac.set_dac_output(ChannelQuad::AllDacs, 0x8000).unwrap();
+
+    log.section("set_config");
+    dac.set_config(Config::default().with_clr_select(true)).unwrap();
+
+    log.section("clear_dacs");
+    dac.clear_dacs().unwrap();
+
+    log.section("load_dacs");
+    dac.load_dacs().unwrap();
+
+    log.section("readback");
+    dac.read(Command::DacRegister(ChannelQuad::DacA)).unwrap();
+    dac.read(Command::RangeSelectRegister(ChannelQuad::DacC)).unwrap();
+    dac.get_power_config().unwrap();
+    dac.get_config().unwrap();
+
+    assert_golden("ad57x4", &log.text());
+}
+
+#[test]
+fn ad57x2() {
+    let spi = Trace::dual(Model::ad57x2());
+    let log = spi.log();
+    let mut dac = Ad57xxShared::new_ad57x2(spi);
+
+    log.section("set_power");
+    dac.set_power(ChannelDual::DacB, true).unwrap();
+    dac.set_power(ChannelDual::AllDacs, true).unwrap();
+    dac.set_power(ChannelDual::DacA, false).unwrap();
+
+    log.section("set_output_range");
+    dac.set_output_range(ChannelDual::AllDacs, OutputRange::Unipolar5V).unwrap();
+    dac.set_output_range(ChannelDual::DacB, OutputRange::Bipolar5V).unwrap();
+
+    log.section("set_dac_output");
+    dac.set_dac_output(ChannelDual::DacB, 0xABCD).unwrap();
+    dac.set_dac_output(ChannelDual::AllDacs, 0x0001).unwrap();
+
+    log.section("set_config");
+    let cfg = Config::default().with_clamp_enable(false);
+    dac.set_config(cfg).unwrap();
+
+    log.section("clear_dacs");
+    dac.clear_dacs().unwrap();
+
+    log.section("load_dacs");
+    dac.load_dacs().unwrap();
+
+    log.section("readback");
+    dac.read(Command::DacRegister(ChannelDual::DacB)).unwrap();
+    dac.read(Command::RangeSelectRegister(ChannelDual::DacB)).unwrap();
+    dac.read(Command::ControlRegister(Function::Config)).unwrap();
+    dac.get_power_config().unwrap();
+
+    assert_golden("ad57x2", &log.text());
+}
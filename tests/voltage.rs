@@ -0,0 +1,103 @@
+use ad57xx::ad57x4::ChannelQuad;
+use ad57xx::coding::Coding;
+use ad57xx::voltage::VREF_DEFAULT;
+use ad57xx::{Ad57xxShared, Error, OutputRange};
+use embedded_hal_mock::eh1::spi::Mock as MockSpi;
+
+mod common;
+use common::{write, Recorder};
+
+#[test]
+fn microvolt_conversions() {
+    let range = OutputRange::Unipolar5V;
+    assert_eq!(range.microvolts_to_code(2_500_000, VREF_DEFAULT), Some(0x8000));
+    assert_eq!(range.microvolts_to_code(5_000_000, VREF_DEFAULT), Some(0xFFFF));
+    assert_eq!(range.microvolts_to_code(-100_000, VREF_DEFAULT), None);
+
+    let range = OutputRange::Bipolar10V;
+    assert_eq!(range.microvolts_to_code(-10_000_000, VREF_DEFAULT), Some(0x0000));
+    assert_eq!(range.microvolts_to_code(0, VREF_DEFAULT), Some(0x8000));
+    assert_eq!(range.microvolts_to_code(10_100_000, VREF_DEFAULT), None);
+    assert_eq!(range.code_to_microvolts(0xC000, VREF_DEFAULT), 5_000_000);
+
+    // Gain of 4.32 with a 3V reference
+    let range = OutputRange::Unipolar10_8V;
+    assert_eq!(range.code_to_microvolts(0x8000, 3_000_000), 6_480_000);
+    assert_eq!(range.microvolts_to_code(6_480_000, 3_000_000), Some(0x8000));
+
+    assert_eq!(OutputRange::Bipolar5V.volts_to_code(-2.5, 2.5), Some(0x4000));
+    assert_eq!(OutputRange::Bipolar5V.code_to_volts(0x4000, 2.5), -2.5);
+    assert_eq!(OutputRange::InvalidReadback.microvolts_to_code(0, VREF_DEFAULT), None);
+}
+
+#[test]
+fn set_dac_microvolts_uses_channel_range_and_coding() {
+    let mut trans = vec![];
+    trans.extend(write([0b00001010, 0x00, 0b100]));
+    trans.extend(write([0b00000010, 0xE0, 0x00]));
+    trans.extend(write([0b00000011, 0x40, 0x00]));
+    let spi = MockSpi::new(&trans);
+
+    let mut dac = Ad57xxShared::new_ad57x4(spi);
+    dac.set_coding(Coding::TwosComplement);
+    dac.set_output_range(ChannelQuad::DacC, OutputRange::Bipolar10V)
+        .unwrap();
+    dac.set_dac_microvolts(ChannelQuad::DacC, -2_500_000).unwrap();
+    assert_eq!(dac.dac_microvolts(ChannelQuad::DacC).unwrap(), -2_500_000);
+    // Channel D is unipolar, the coding does not apply
+    dac.set_dac_volts(ChannelQuad::DacD, 1.25).unwrap();
+    assert!(matches!(
+        dac.set_dac_microvolts(ChannelQuad::DacD, -100_000),
+        Err(Error::InvalidArgument)
+    ));
+    dac.destroy().done();
+}
+
+#[cfg(feature = "fixed")]
+#[test]
+fn fixed_point_conversions() {
+    use fixed::types::I16F16;
+    let range = OutputRange::Bipolar5V;
+    let vref = I16F16::from_num(2.5);
+    assert_eq!(range.fixed_to_code(I16F16::from_num(2.5), vref), Some(0xC000));
+    assert_eq!(range.code_to_fixed(0xC000, vref), I16F16::from_num(2.5));
+    assert_eq!(range.fixed_to_code(I16F16::from_num(-6), vref), None);
+    // Out of the microvolt range instead of wrapping around
+    let huge = I16F16::from_num(4300);
+    assert_eq!(range.fixed_to_code(huge, vref), None);
+    assert_eq!(range.fixed_to_code(I16F16::from_num(2.5), huge), None);
+
+    let mut dac = Ad57xxShared::new_ad57x4(MockSpi::new(&[]));
+    assert!(matches!(
+        dac.set_dac_fixed(ChannelQuad::DacA, huge),
+        Err(Error::InvalidArgument)
+    ));
+    dac.destroy().done();
+}
+
+#[cfg(feature = "uom")]
+#[test]
+fn set_dac_potential() {
+    use uom::si::electric_potential::millivolt;
+    use uom::si::f32::ElectricPotential;
+    let trans = write([0b00000000, 0x40, 0x00]);
+    let spi = MockSpi::new(&trans);
+
+    let mut dac = Ad57xxShared::new_ad57x4(spi);
+    dac.set_dac_potential(ChannelQuad::DacA, ElectricPotential::new::<millivolt>(1250.0))
+        .unwrap();
+    dac.destroy().done();
+}
+
+#[test]
+fn all_dacs_rejected_as_a_whole() {
+    let mut dac = Ad57xxShared::new_ad57x4(Recorder::default());
+    dac.set_output_range(ChannelQuad::DacA, OutputRange::Bipolar10V)
+        .unwrap();
+    // Negative voltages are valid for channel A only, nothing is written
+    assert!(matches!(
+        dac.set_dac_microvolts(ChannelQuad::AllDacs, -1_000_000),
+        Err(Error::InvalidArgument)
+    ));
+    assert_eq!(dac.destroy().frames, [[0x08, 0x00, 0x04]]);
+}
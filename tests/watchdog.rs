@@ -0,0 +1,75 @@
+use ad57xx::ad57x4::ChannelQuad;
+use ad57xx::model::Model;
+use ad57xx::watchdog::{SafeState, Status, Supervisor, SupervisorError};
+use ad57xx::{Ad57xxShared, Error};
+use embedded_hal_mock::eh1::pin::{Mock as PinMock, State, Transaction as PinTransaction};
+
+mod common;
+use common::Recorder;
+
+#[test]
+fn trips_to_safe_codes_and_latches() {
+    let mut dac = Ad57xxShared::new_ad57x4(Model::ad57x4());
+    dac.set_dac_output(ChannelQuad::DacB, 0xFFFF).unwrap();
+    let mut sup = Supervisor::new(dac, 3, SafeState::codes([0x8000, 0x8001, 0x8002, 0x8003]));
+
+    for _ in 0..4 {
+        assert_eq!(sup.tick().unwrap(), Status::Running);
+        sup.feed();
+    }
+    assert_eq!(sup.tick().unwrap(), Status::Running);
+    assert_eq!(sup.tick().unwrap(), Status::Running);
+    assert_eq!(sup.tick().unwrap(), Status::Tripped);
+    assert!(sup.is_faulted());
+    assert!(sup.dac().is_none());
+
+    // Feeding does not clear the fault
+    sup.feed();
+    assert_eq!(sup.tick().unwrap(), Status::Faulted);
+    assert!(sup.acknowledge());
+    assert!(!sup.acknowledge());
+    sup.dac()
+        .unwrap()
+        .set_dac_output(ChannelQuad::DacA, 0x1234)
+        .unwrap();
+
+    let (dac, _) = sup.destroy();
+    let model = dac.destroy();
+    assert_eq!(model.dac_register(0), Some(0x1234));
+    assert_eq!(model.dac_register(1), Some(0x8001));
+    assert_eq!(model.dac_register(3), Some(0x8003));
+}
+
+#[test]
+fn clear_pin() {
+    let clr = PinMock::new(&[
+        PinTransaction::set(State::Low),
+        PinTransaction::set(State::High),
+    ]);
+    let dac = Ad57xxShared::new_ad57x2(Recorder::default());
+    let mut sup = Supervisor::new(dac, 1, SafeState::ClearPin(clr));
+    assert_eq!(sup.tick().unwrap(), Status::Tripped);
+    assert_eq!(sup.tick().unwrap(), Status::Faulted);
+    let (dac, SafeState::ClearPin(mut clr)) = sup.destroy() else {
+        unreachable!()
+    };
+    assert!(dac.destroy().frames.is_empty());
+    clr.done();
+}
+
+#[test]
+fn failed_safe_state_is_retried() {
+    let dac = Ad57xxShared::new_ad57x4(Recorder {
+        fail: true,
+        ..Default::default()
+    });
+    let mut sup = Supervisor::new(dac, 2, SafeState::clear());
+    sup.trip().unwrap_err();
+    for _ in 0..2 {
+        assert!(matches!(
+            sup.tick(),
+            Err(SupervisorError::Dac(Error::Spi(_)))
+        ));
+    }
+    assert!(sup.is_faulted());
+}